@@ -0,0 +1,68 @@
+use bevy::prelude::*;
+use prima::prelude::*;
+
+use super::{BroadPhase, Proxy, ProxyList};
+use crate::components::CollisionGroups;
+
+/// Tests every proxy against every other proxy. Hopeless for large scenes, but it makes a good baseline to benchmark against.
+#[derive(Debug, Clone, Default)]
+pub struct BruteForce {
+    proxies: ProxyList,
+}
+
+impl BruteForce {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl BroadPhase for BruteForce {
+    fn insert(&mut self, entity: Entity, bounds: Aabr<f32>, groups: CollisionGroups) {
+        self.proxies.insert(Proxy::new(entity, bounds, groups));
+    }
+
+    fn update(&mut self, entity: Entity, bounds: Aabr<f32>, groups: CollisionGroups) {
+        self.insert(entity, bounds, groups);
+    }
+
+    fn remove(&mut self, entity: Entity) {
+        self.proxies.remove(entity);
+    }
+
+    fn pairs(&mut self) -> Vec<(Entity, Entity)> {
+        let mut pairs = Vec::new();
+        let proxies = self.proxies.as_slice();
+        for (i, a) in proxies.iter().enumerate() {
+            for b in proxies[i + 1..].iter() {
+                if a.interacts(b) && a.overlaps(b) {
+                    pairs.push((a.entity, b.entity));
+                }
+            }
+        }
+        pairs
+    }
+
+    fn query(&self, region: &Aabr<f32>, groups: CollisionGroups) -> Vec<Entity> {
+        self.proxies.query(region, groups)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pairs_overlapping_proxies_whose_groups_agree() {
+        let [a, b, c] = [0, 1, 2].map(Entity::from_raw);
+        let bounds = Aabr::new(Point::new(0.0, 0.0), Point::new(1.0, 1.0));
+        let mut brute = BruteForce::new();
+        brute.insert(a, bounds, CollisionGroups::new(1, 1));
+        brute.insert(b, bounds, CollisionGroups::new(1, 1));
+        brute.insert(c, bounds, CollisionGroups::new(2, 2));
+        assert_eq!(brute.pairs(), vec![(a, b)]);
+
+        brute.remove(a);
+        brute.update(c, bounds, CollisionGroups::new(3, 3));
+        assert_eq!(brute.pairs(), vec![(c, b)]);
+    }
+}
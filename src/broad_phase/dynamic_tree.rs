@@ -0,0 +1,423 @@
+use bevy::{prelude::*, utils::HashMap};
+use prima::prelude::*;
+
+use super::{BroadPhase, Proxy};
+use crate::components::CollisionGroups;
+
+pub const DEFAULT_MARGIN: f32 = 0.1;
+
+const NULL: usize = usize::MAX;
+
+#[derive(Debug, Clone)]
+struct Node {
+    /// Fattened bounds for leaves, the union of both children for branches.
+    min: Vec2,
+    max: Vec2,
+    parent: usize,
+    left: usize,
+    right: usize,
+    /// Longest path down to a leaf, which is 0 for leaves themselves.
+    height: usize,
+    /// The tight bounds of the collider, only set on leaves.
+    proxy: Option<Proxy>,
+}
+
+impl Node {
+    fn is_leaf(&self) -> bool {
+        self.left == NULL
+    }
+}
+
+/// An incrementally maintained bounding volume tree. Each leaf stores a fattened copy of its collider's bounds, and is only
+/// moved around the tree once the collider leaves that box, so most frames touch very little of the tree.
+///
+/// Use it as the plugin's broad phase and it doubles as a spatial index: any system can take `Res<DynamicAabbTree>` and
+/// [BroadPhase::query] it for what is inside a region.
+#[derive(Debug, Clone)]
+pub struct DynamicAabbTree {
+    margin: f32,
+    root: usize,
+    nodes: Vec<Node>,
+    free: Vec<usize>,
+    lookup: HashMap<Entity, usize>,
+}
+
+impl DynamicAabbTree {
+    pub fn new(margin: f32) -> Self {
+        Self {
+            margin,
+            root: NULL,
+            nodes: Vec::new(),
+            free: Vec::new(),
+            lookup: HashMap::default(),
+        }
+    }
+
+    pub fn margin(&self) -> f32 {
+        self.margin
+    }
+
+    /// Calls `f` for every leaf whose fattened bounds overlap the region.
+    fn query_leaves(&self, min: Vec2, max: Vec2, mut f: impl FnMut(usize)) {
+        if self.root == NULL {
+            return;
+        }
+        let mut stack = vec![self.root];
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if node.min.x > max.x || min.x > node.max.x || node.min.y > max.y || min.y > node.max.y {
+                continue;
+            }
+            if node.is_leaf() {
+                f(index);
+            } else {
+                stack.push(node.left);
+                stack.push(node.right);
+            }
+        }
+    }
+
+    fn allocate(&mut self, node: Node) -> usize {
+        if let Some(index) = self.free.pop() {
+            self.nodes[index] = node;
+            index
+        } else {
+            self.nodes.push(node);
+            self.nodes.len() - 1
+        }
+    }
+
+    fn release(&mut self, index: usize) {
+        self.nodes[index].proxy = None;
+        self.free.push(index);
+    }
+
+    fn fatten(&self, proxy: &Proxy) -> (Vec2, Vec2) {
+        let margin = Vec2::new(self.margin, self.margin);
+        (proxy.min - margin, proxy.max + margin)
+    }
+
+    fn insert_leaf(&mut self, leaf: usize) {
+        if self.root == NULL {
+            self.root = leaf;
+            self.nodes[leaf].parent = NULL;
+            return;
+        }
+
+        // Walk down the tree, picking whichever branch grows the least by taking on the new leaf.
+        let leaf_min = self.nodes[leaf].min;
+        let leaf_max = self.nodes[leaf].max;
+        let mut index = self.root;
+        while !self.nodes[index].is_leaf() {
+            let node = &self.nodes[index];
+            let area = perimeter(node.min, node.max);
+            let combined = perimeter(node.min.min(leaf_min), node.max.max(leaf_max));
+
+            // Cost of making a new parent for this node and the leaf, and the cost pushed down onto the children.
+            let cost = 2.0 * combined;
+            let inheritance = 2.0 * (combined - area);
+
+            let child_cost = |child: &Node| {
+                let grown = perimeter(child.min.min(leaf_min), child.max.max(leaf_max));
+                if child.is_leaf() {
+                    grown + inheritance
+                } else {
+                    grown - perimeter(child.min, child.max) + inheritance
+                }
+            };
+            let cost_left = child_cost(&self.nodes[node.left]);
+            let cost_right = child_cost(&self.nodes[node.right]);
+
+            if cost < cost_left && cost < cost_right {
+                break;
+            }
+            index = if cost_left < cost_right { node.left } else { node.right };
+        }
+
+        let sibling = index;
+        let old_parent = self.nodes[sibling].parent;
+        let new_parent = self.allocate(Node {
+            min: self.nodes[sibling].min.min(leaf_min),
+            max: self.nodes[sibling].max.max(leaf_max),
+            parent: old_parent,
+            left: sibling,
+            right: leaf,
+            height: self.nodes[sibling].height + 1,
+            proxy: None,
+        });
+
+        if old_parent == NULL {
+            self.root = new_parent;
+        } else if self.nodes[old_parent].left == sibling {
+            self.nodes[old_parent].left = new_parent;
+        } else {
+            self.nodes[old_parent].right = new_parent;
+        }
+        self.nodes[sibling].parent = new_parent;
+        self.nodes[leaf].parent = new_parent;
+
+        self.refit(old_parent);
+    }
+
+    fn remove_leaf(&mut self, leaf: usize) {
+        if leaf == self.root {
+            self.root = NULL;
+            return;
+        }
+
+        let parent = self.nodes[leaf].parent;
+        let grand_parent = self.nodes[parent].parent;
+        let sibling = if self.nodes[parent].left == leaf {
+            self.nodes[parent].right
+        } else {
+            self.nodes[parent].left
+        };
+
+        // The parent only existed to join the leaf and its sibling, so the sibling takes its place.
+        if grand_parent == NULL {
+            self.root = sibling;
+        } else if self.nodes[grand_parent].left == parent {
+            self.nodes[grand_parent].left = sibling;
+        } else {
+            self.nodes[grand_parent].right = sibling;
+        }
+        self.nodes[sibling].parent = grand_parent;
+        self.release(parent);
+        self.refit(grand_parent);
+    }
+
+    /// Recalculates the bounds and height of every branch from `index` up to the root, rebalancing each one on the way.
+    fn refit(&mut self, mut index: usize) {
+        while index != NULL {
+            index = self.balance(index);
+            self.fit(index);
+            index = self.nodes[index].parent;
+        }
+    }
+
+    /// Recalculates the bounds and height of a branch from its children.
+    fn fit(&mut self, index: usize) {
+        let left = &self.nodes[self.nodes[index].left];
+        let right = &self.nodes[self.nodes[index].right];
+        let (min, max) = (left.min.min(right.min), left.max.max(right.max));
+        let height = 1 + left.height.max(right.height);
+        let node = &mut self.nodes[index];
+        node.min = min;
+        node.max = max;
+        node.height = height;
+    }
+
+    /// If one child of the branch is more than a level taller than the other, rotates the taller one up into the branch's
+    /// place. Returns whichever node is now at the top of the subtree.
+    fn balance(&mut self, index: usize) -> usize {
+        let node = &self.nodes[index];
+        if node.is_leaf() || node.height < 2 {
+            return index;
+        }
+        let (left, right) = (node.left, node.right);
+        let (left_height, right_height) = (self.nodes[left].height, self.nodes[right].height);
+        if right_height > left_height + 1 {
+            self.rotate_up(index, right)
+        } else if left_height > right_height + 1 {
+            self.rotate_up(index, left)
+        } else {
+            index
+        }
+    }
+
+    /// Swaps a branch with one of its children. The child keeps its taller child and hands the shorter one down to the
+    /// old branch, which becomes its other child.
+    fn rotate_up(&mut self, index: usize, child: usize) -> usize {
+        let parent = self.nodes[index].parent;
+        let (a, b) = (self.nodes[child].left, self.nodes[child].right);
+        let (keep, give) = if self.nodes[a].height > self.nodes[b].height { (a, b) } else { (b, a) };
+
+        self.nodes[child].parent = parent;
+        if parent == NULL {
+            self.root = child;
+        } else if self.nodes[parent].left == index {
+            self.nodes[parent].left = child;
+        } else {
+            self.nodes[parent].right = child;
+        }
+        self.nodes[child].left = index;
+        self.nodes[child].right = keep;
+        self.nodes[index].parent = child;
+
+        if self.nodes[index].left == child {
+            self.nodes[index].left = give;
+        } else {
+            self.nodes[index].right = give;
+        }
+        self.nodes[give].parent = index;
+
+        self.fit(index);
+        self.fit(child);
+        child
+    }
+}
+
+impl Default for DynamicAabbTree {
+    fn default() -> Self {
+        Self::new(DEFAULT_MARGIN)
+    }
+}
+
+impl BroadPhase for DynamicAabbTree {
+    fn insert(&mut self, entity: Entity, bounds: Aabr<f32>, groups: CollisionGroups) {
+        if self.lookup.contains_key(&entity) {
+            self.update(entity, bounds, groups);
+            return;
+        }
+        let proxy = Proxy::new(entity, bounds, groups);
+        let (min, max) = self.fatten(&proxy);
+        let leaf = self.allocate(Node {
+            min,
+            max,
+            parent: NULL,
+            left: NULL,
+            right: NULL,
+            height: 0,
+            proxy: Some(proxy),
+        });
+        self.lookup.insert(entity, leaf);
+        self.insert_leaf(leaf);
+    }
+
+    fn update(&mut self, entity: Entity, bounds: Aabr<f32>, groups: CollisionGroups) {
+        let leaf = match self.lookup.get(&entity) {
+            Some(leaf) => *leaf,
+            None => {
+                self.insert(entity, bounds, groups);
+                return;
+            }
+        };
+
+        let proxy = Proxy::new(entity, bounds, groups);
+        self.nodes[leaf].proxy = Some(proxy);
+
+        // Still inside the fat box, so the tree does not need to change.
+        let node = &self.nodes[leaf];
+        if proxy.min.x >= node.min.x
+            && proxy.min.y >= node.min.y
+            && proxy.max.x <= node.max.x
+            && proxy.max.y <= node.max.y
+        {
+            return;
+        }
+
+        self.remove_leaf(leaf);
+        let (min, max) = self.fatten(&proxy);
+        self.nodes[leaf].min = min;
+        self.nodes[leaf].max = max;
+        self.insert_leaf(leaf);
+    }
+
+    fn remove(&mut self, entity: Entity) {
+        if let Some(leaf) = self.lookup.remove(&entity) {
+            self.remove_leaf(leaf);
+            self.release(leaf);
+        }
+    }
+
+    fn pairs(&mut self) -> Vec<(Entity, Entity)> {
+        let mut pairs = Vec::new();
+        for leaf in self.lookup.values() {
+            let a = self.nodes[*leaf].proxy.unwrap();
+            self.query_leaves(a.min, a.max, |other| {
+                // Each pair is found from both sides, so only keep it from the lower leaf.
+                if other <= *leaf {
+                    return;
+                }
+                let b = self.nodes[other].proxy.unwrap();
+                if a.interacts(&b) && a.overlaps(&b) {
+                    pairs.push((a.entity, b.entity));
+                }
+            });
+        }
+        pairs
+    }
+
+    fn query(&self, region: &Aabr<f32>, groups: CollisionGroups) -> Vec<Entity> {
+        let min = Vec2::new(region.min.x, region.min.y);
+        let max = Vec2::new(region.max.x, region.max.y);
+        let mut entities = Vec::new();
+        self.query_leaves(min, max, |leaf| {
+            let proxy = self.nodes[leaf].proxy.unwrap();
+            if groups.interacts_with(&proxy.groups) && proxy.overlaps_region(region) {
+                entities.push(proxy.entity);
+            }
+        });
+        entities
+    }
+}
+
+fn perimeter(min: Vec2, max: Vec2) -> f32 {
+    let size = max - min;
+    2.0 * (size.x + size.y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bounds(x: f32, y: f32) -> Aabr<f32> {
+        Aabr::new(Point::new(x, y), Point::new(x + 1.0, y + 1.0))
+    }
+
+    /// Checks the links, bounds and heights of every branch below `index`, returning its height.
+    fn validate(tree: &DynamicAabbTree, index: usize) -> usize {
+        let node = &tree.nodes[index];
+        if node.is_leaf() {
+            assert_eq!(node.height, 0);
+            return 0;
+        }
+        let (left, right) = (&tree.nodes[node.left], &tree.nodes[node.right]);
+        assert_eq!((left.parent, right.parent), (index, index));
+        assert_eq!(node.min, left.min.min(right.min));
+        assert_eq!(node.max, left.max.max(right.max));
+        let height = 1 + validate(tree, node.left).max(validate(tree, node.right));
+        assert_eq!(node.height, height);
+        height
+    }
+
+    #[test]
+    fn stays_balanced_when_filled_in_order() {
+        let mut tree = DynamicAabbTree::default();
+        for i in 0..64 {
+            tree.insert(Entity::from_raw(i), bounds(i as f32 * 2.0, 0.0), CollisionGroups::new(1, 1));
+        }
+        assert_eq!(tree.nodes[tree.root].parent, NULL);
+        // A perfectly balanced tree of 64 leaves is 6 deep, where an unbalanced one could be 63.
+        assert!(validate(&tree, tree.root) <= 8);
+
+        for i in (0..64).step_by(2) {
+            tree.remove(Entity::from_raw(i));
+        }
+        assert!(validate(&tree, tree.root) <= 7);
+        assert!(tree.pairs().is_empty());
+        assert_eq!(tree.query(&bounds(3.0, 0.0), CollisionGroups::new(1, 1)), vec![Entity::from_raw(1)]);
+    }
+
+    #[test]
+    fn leaves_only_move_once_they_leave_their_fat_bounds() {
+        let (a, b) = (Entity::from_raw(0), Entity::from_raw(1));
+        let mut tree = DynamicAabbTree::new(0.5);
+        tree.insert(a, bounds(0.0, 0.0), CollisionGroups::new(1, 1));
+        tree.insert(b, bounds(1.2, 0.0), CollisionGroups::new(1, 1));
+        // Fattened boxes overlap, but the colliders themselves do not.
+        assert!(tree.pairs().is_empty());
+
+        let leaf = tree.lookup[&a];
+        let fat_min = tree.nodes[leaf].min;
+        tree.update(a, bounds(0.3, 0.0), CollisionGroups::new(1, 1));
+        assert_eq!(tree.nodes[leaf].min, fat_min);
+        assert_eq!(tree.pairs(), vec![(a, b)]);
+
+        tree.update(a, bounds(10.0, 0.0), CollisionGroups::new(1, 1));
+        assert_eq!(tree.nodes[leaf].min, Vec2::new(9.5, -0.5));
+        assert!(tree.pairs().is_empty());
+        assert_eq!(tree.query(&bounds(9.0, 0.0), CollisionGroups::new(1, 1)), vec![a]);
+        validate(&tree, tree.root);
+    }
+}
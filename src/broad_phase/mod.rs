@@ -0,0 +1,187 @@
+mod brute_force;
+mod dynamic_tree;
+mod quad_tree;
+mod spatial_hash;
+mod sweep_and_prune;
+
+pub use brute_force::BruteForce;
+pub use dynamic_tree::DynamicAabbTree;
+pub use quad_tree::QuadTree;
+pub use spatial_hash::SpatialHashGrid;
+pub use sweep_and_prune::SweepAndPrune;
+
+use bevy::{ecs::system::Resource, prelude::*, utils::HashMap};
+use prima::prelude::*;
+
+use crate::components::CollisionGroups;
+
+/// A trait that allows the struct to be used as the broad phase of the pipeline. The broad phase is told about every
+/// collider's world-space bounds and is expected to return the pairs whose bounds overlap. Which one suits best depends on
+/// how the objects in your scene are spread out, so pick whichever one benchmarks best.
+///
+/// The plugin only creates the broad phase if there is not one already, so insert your own instance as a resource before
+/// adding the plugin to tune it.
+pub trait BroadPhase: Default + Resource {
+    /// Adds a new proxy to the broad phase.
+    fn insert(&mut self, entity: Entity, bounds: Aabr<f32>, groups: CollisionGroups);
+    /// Updates the bounds of an existing proxy. Proxies that have not been inserted yet are added.
+    fn update(&mut self, entity: Entity, bounds: Aabr<f32>, groups: CollisionGroups);
+    /// Removes a proxy from the broad phase.
+    fn remove(&mut self, entity: Entity);
+    /// Finds every pair of proxies whose bounds overlap and whose collision groups accept each other.
+    fn pairs(&mut self) -> Vec<(Entity, Entity)>;
+    /// Finds every proxy whose bounds overlap the region and whose collision groups accept the given ones. Proxies are only
+    /// as fresh as the last physics step.
+    fn query(&self, region: &Aabr<f32>, groups: CollisionGroups) -> Vec<Entity>;
+}
+
+/// The bounds of a single entity, as seen by the broad phase.
+#[derive(Debug, Clone, Copy)]
+pub struct Proxy {
+    pub entity: Entity,
+    pub min: Vec2,
+    pub max: Vec2,
+    pub groups: CollisionGroups,
+}
+
+impl Proxy {
+    pub fn new(entity: Entity, bounds: Aabr<f32>, groups: CollisionGroups) -> Self {
+        Self {
+            entity,
+            min: Vec2::new(bounds.min.x, bounds.min.y),
+            max: Vec2::new(bounds.max.x, bounds.max.y),
+            groups,
+        }
+    }
+
+    /// True if the two proxies' bounds overlap.
+    pub fn overlaps(&self, other: &Proxy) -> bool {
+        self.min.x <= other.max.x
+            && other.min.x <= self.max.x
+            && self.min.y <= other.max.y
+            && other.min.y <= self.max.y
+    }
+
+    /// True if the proxy's bounds overlap the region.
+    pub fn overlaps_region(&self, region: &Aabr<f32>) -> bool {
+        self.min.x <= region.max.x
+            && region.min.x <= self.max.x
+            && self.min.y <= region.max.y
+            && region.min.y <= self.max.y
+    }
+
+    /// True if the two proxies' collision groups accept each other.
+    pub fn interacts(&self, other: &Proxy) -> bool {
+        self.groups.interacts_with(&other.groups)
+    }
+}
+
+/// A flat list of proxies with a lookup from each entity to where its proxy is. Removing a proxy moves the last one into
+/// the gap, so indices stay packed but are only stable until the next removal.
+#[derive(Debug, Clone, Default)]
+pub struct ProxyList {
+    proxies: Vec<Proxy>,
+    lookup: HashMap<Entity, usize>,
+}
+
+impl ProxyList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the proxy, or replaces the one its entity already has. Returns where it ended up and the proxy it replaced.
+    pub fn insert(&mut self, proxy: Proxy) -> (usize, Option<Proxy>) {
+        if let Some(&index) = self.lookup.get(&proxy.entity) {
+            (index, Some(std::mem::replace(&mut self.proxies[index], proxy)))
+        } else {
+            let index = self.proxies.len();
+            self.lookup.insert(proxy.entity, index);
+            self.proxies.push(proxy);
+            (index, None)
+        }
+    }
+
+    /// Removes the entity's proxy, returning where it was and what it held. Whatever was last is now at that index.
+    pub fn remove(&mut self, entity: Entity) -> Option<(usize, Proxy)> {
+        let index = self.lookup.remove(&entity)?;
+        let proxy = self.proxies.swap_remove(index);
+        if let Some(moved) = self.proxies.get(index) {
+            self.lookup.insert(moved.entity, index);
+        }
+        Some((index, proxy))
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Proxy> {
+        self.proxies.get(index)
+    }
+
+    pub fn len(&self) -> usize {
+        self.proxies.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.proxies.is_empty()
+    }
+
+    pub fn as_slice(&self) -> &[Proxy] {
+        &self.proxies
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Proxy> {
+        self.proxies.iter()
+    }
+
+    /// Every proxy overlapping the region whose collision groups accept the given ones.
+    pub fn query(&self, region: &Aabr<f32>, groups: CollisionGroups) -> Vec<Entity> {
+        self.proxies
+            .iter()
+            .filter(|proxy| groups.interacts_with(&proxy.groups) && proxy.overlaps_region(region))
+            .map(|proxy| proxy.entity)
+            .collect()
+    }
+}
+
+impl std::ops::Index<usize> for ProxyList {
+    type Output = Proxy;
+
+    fn index(&self, index: usize) -> &Proxy {
+        &self.proxies[index]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn proxy(id: u32, x: f32) -> Proxy {
+        let bounds = Aabr::new(Point::new(x, 0.0), Point::new(x + 1.0, 1.0));
+        Proxy::new(Entity::from_raw(id), bounds, CollisionGroups::default())
+    }
+
+    #[test]
+    fn removing_moves_the_last_proxy_into_the_gap() {
+        let mut list = ProxyList::new();
+        for id in 0..4 {
+            let (index, replaced) = list.insert(proxy(id, id as f32));
+            assert_eq!(index, id as usize);
+            assert!(replaced.is_none());
+        }
+
+        let (index, removed) = list.remove(Entity::from_raw(1)).unwrap();
+        assert_eq!((index, removed.entity), (1, Entity::from_raw(1)));
+        assert_eq!(list[1].entity, Entity::from_raw(3));
+        assert!(list.remove(Entity::from_raw(1)).is_none());
+
+        // The moved proxy is found at its new index.
+        let (index, replaced) = list.insert(proxy(3, 10.0));
+        assert_eq!(index, 1);
+        assert_eq!(replaced.unwrap().min.x, 3.0);
+        assert_eq!(list[1].min.x, 10.0);
+
+        // Removing the last proxy has nothing to move.
+        assert_eq!(list.remove(Entity::from_raw(2)).unwrap().0, 2);
+        assert_eq!(list.len(), 2);
+        assert_eq!(list.remove(Entity::from_raw(3)).unwrap().0, 1);
+        assert_eq!(list.iter().map(|p| p.entity).collect::<Vec<_>>(), vec![Entity::from_raw(0)]);
+    }
+}
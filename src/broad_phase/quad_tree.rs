@@ -0,0 +1,265 @@
+use bevy::prelude::*;
+use prima::prelude::*;
+
+use super::{BroadPhase, Proxy, ProxyList};
+use crate::components::CollisionGroups;
+
+pub const DEFAULT_MAX_DEPTH: usize = 8;
+pub const DEFAULT_NODE_CAPACITY: usize = 8;
+
+#[derive(Debug, Clone)]
+struct Node {
+    min: Vec2,
+    max: Vec2,
+    /// Proxies that do not fit entirely inside any one child.
+    items: Vec<usize>,
+    children: Option<[usize; 4]>,
+}
+
+impl Node {
+    fn new(min: Vec2, max: Vec2) -> Self {
+        Self {
+            min,
+            max,
+            items: Vec::new(),
+            children: None,
+        }
+    }
+}
+
+/// A quadtree that is rebuilt over the bounds of the whole scene every time pairs are requested, and kept up to date in
+/// between so queries can use it. Works best when objects are clumped together with lots of empty space in between.
+#[derive(Debug, Clone)]
+pub struct QuadTree {
+    max_depth: usize,
+    node_capacity: usize,
+    proxies: ProxyList,
+    nodes: Vec<Node>,
+}
+
+impl QuadTree {
+    pub fn new(max_depth: usize, node_capacity: usize) -> Self {
+        Self {
+            max_depth,
+            node_capacity,
+            proxies: ProxyList::new(),
+            nodes: Vec::new(),
+        }
+    }
+
+    fn build(&mut self) {
+        self.nodes.clear();
+        if self.proxies.is_empty() {
+            return;
+        }
+
+        let mut min = self.proxies[0].min;
+        let mut max = self.proxies[0].max;
+        for proxy in self.proxies.iter() {
+            min = min.min(proxy.min);
+            max = max.max(proxy.max);
+        }
+        self.nodes.push(Node::new(min, max));
+
+        for index in 0..self.proxies.len() {
+            self.insert_into(0, index, 0);
+        }
+    }
+
+    fn insert_into(&mut self, node: usize, index: usize, depth: usize) {
+        if let Some(children) = self.nodes[node].children {
+            if let Some(child) = self.child_containing(children, &self.proxies[index]) {
+                self.insert_into(child, index, depth + 1);
+            } else {
+                self.nodes[node].items.push(index);
+            }
+            return;
+        }
+
+        self.nodes[node].items.push(index);
+        if self.nodes[node].items.len() > self.node_capacity && depth < self.max_depth {
+            self.split(node, depth);
+        }
+    }
+
+    fn split(&mut self, node: usize, depth: usize) {
+        let min = self.nodes[node].min;
+        let max = self.nodes[node].max;
+        let center = (min + max) * 0.5;
+        let quadrants = [
+            (min, center),
+            (Vec2::new(center.x, min.y), Vec2::new(max.x, center.y)),
+            (Vec2::new(min.x, center.y), Vec2::new(center.x, max.y)),
+            (center, max),
+        ];
+
+        let first = self.nodes.len();
+        for (quad_min, quad_max) in quadrants {
+            self.nodes.push(Node::new(quad_min, quad_max));
+        }
+        self.nodes[node].children = Some([first, first + 1, first + 2, first + 3]);
+
+        let items = std::mem::take(&mut self.nodes[node].items);
+        for index in items {
+            self.insert_into(node, index, depth);
+        }
+    }
+
+    fn child_containing(&self, children: [usize; 4], proxy: &Proxy) -> Option<usize> {
+        children.into_iter().find(|child| {
+            let node = &self.nodes[*child];
+            proxy.min.x >= node.min.x
+                && proxy.min.y >= node.min.y
+                && proxy.max.x <= node.max.x
+                && proxy.max.y <= node.max.y
+        })
+    }
+
+    /// The node holding a proxy with these bounds. Anything that does not fit inside the root is held by the root too.
+    fn locate(&self, proxy: &Proxy) -> usize {
+        let mut node = 0;
+        while let Some(child) = self.nodes[node].children.and_then(|children| self.child_containing(children, proxy)) {
+            node = child;
+        }
+        node
+    }
+
+    /// Takes a proxy out of the tree, using the bounds it was put in with.
+    fn detach(&mut self, index: usize, proxy: &Proxy) {
+        if !self.nodes.is_empty() {
+            let node = self.locate(proxy);
+            self.nodes[node].items.retain(|i| *i != index);
+        }
+    }
+
+    /// Puts a proxy into the tree, starting one around it if there is none yet.
+    fn attach(&mut self, index: usize) {
+        if self.nodes.is_empty() {
+            let proxy = self.proxies[index];
+            self.nodes.push(Node::new(proxy.min, proxy.max));
+        }
+        self.insert_into(0, index, 0);
+    }
+
+    /// Tests each proxy in the node against its neighbours and everything held further up the tree.
+    fn collect(&self, node: usize, ancestors: &mut Vec<usize>, pairs: &mut Vec<(Entity, Entity)>) {
+        let node = &self.nodes[node];
+        for (i, index_a) in node.items.iter().enumerate() {
+            let a = &self.proxies[*index_a];
+            for index_b in ancestors.iter().chain(node.items[i + 1..].iter()) {
+                let b = &self.proxies[*index_b];
+                if a.interacts(b) && a.overlaps(b) {
+                    pairs.push((a.entity, b.entity));
+                }
+            }
+        }
+
+        if let Some(children) = node.children {
+            let len = ancestors.len();
+            ancestors.extend(node.items.iter().copied());
+            for child in children {
+                self.collect(child, ancestors, pairs);
+            }
+            ancestors.truncate(len);
+        }
+    }
+}
+
+impl Default for QuadTree {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_DEPTH, DEFAULT_NODE_CAPACITY)
+    }
+}
+
+impl BroadPhase for QuadTree {
+    fn insert(&mut self, entity: Entity, bounds: Aabr<f32>, groups: CollisionGroups) {
+        let (index, previous) = self.proxies.insert(Proxy::new(entity, bounds, groups));
+        if let Some(previous) = previous {
+            self.detach(index, &previous);
+        }
+        self.attach(index);
+    }
+
+    fn update(&mut self, entity: Entity, bounds: Aabr<f32>, groups: CollisionGroups) {
+        self.insert(entity, bounds, groups);
+    }
+
+    fn remove(&mut self, entity: Entity) {
+        if let Some((index, proxy)) = self.proxies.remove(entity) {
+            self.detach(index, &proxy);
+            // The last proxy was moved into the gap, so the node holding it needs to know where it went.
+            if let Some(moved) = self.proxies.get(index).copied() {
+                let last = self.proxies.len();
+                let node = self.locate(&moved);
+                self.nodes[node].items.iter_mut().filter(|i| **i == last).for_each(|i| *i = index);
+            }
+        }
+    }
+
+    fn pairs(&mut self) -> Vec<(Entity, Entity)> {
+        self.build();
+        let mut pairs = Vec::new();
+        if !self.nodes.is_empty() {
+            self.collect(0, &mut Vec::new(), &mut pairs);
+        }
+        pairs
+    }
+
+    fn query(&self, region: &Aabr<f32>, groups: CollisionGroups) -> Vec<Entity> {
+        let accepts = |proxy: &Proxy| groups.interacts_with(&proxy.groups) && proxy.overlaps_region(region);
+        let mut entities = Vec::new();
+        if self.nodes.is_empty() {
+            return entities;
+        }
+        let mut stack = vec![0];
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            // The root also holds whatever has moved outside it since the last rebuild, so it is always checked.
+            if index != 0
+                && (node.min.x > region.max.x
+                    || region.min.x > node.max.x
+                    || node.min.y > region.max.y
+                    || region.min.y > node.max.y)
+            {
+                continue;
+            }
+            entities.extend(node.items.iter().map(|i| &self.proxies[*i]).filter(|p| accepts(p)).map(|p| p.entity));
+            if let Some(children) = node.children {
+                stack.extend(children);
+            }
+        }
+        entities
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bounds(min: (f32, f32), max: (f32, f32)) -> Aabr<f32> {
+        Aabr::new(Point::new(min.0, min.1), Point::new(max.0, max.1))
+    }
+
+    #[test]
+    fn full_nodes_split_and_keep_straddlers() {
+        let [a, b, c, d, e] = [0, 1, 2, 3, 4].map(Entity::from_raw);
+        let mut tree = QuadTree::new(4, 2);
+        tree.insert(a, bounds((0.0, 0.0), (1.0, 1.0)), CollisionGroups::new(1, 1));
+        tree.insert(b, bounds((7.0, 0.0), (8.0, 1.0)), CollisionGroups::new(1, 1));
+        tree.insert(c, bounds((0.0, 7.0), (1.0, 8.0)), CollisionGroups::new(1, 1));
+        tree.insert(d, bounds((7.0, 7.0), (8.0, 8.0)), CollisionGroups::new(1, 1));
+        // Crosses the middle of the scene, so it cannot go into any one quadrant.
+        tree.insert(e, bounds((0.5, 0.5), (7.5, 1.5)), CollisionGroups::new(1, 1));
+
+        let mut pairs: Vec<_> = tree.pairs().into_iter().map(|(x, y)| (x.min(y), x.max(y))).collect();
+        pairs.sort();
+        assert_eq!(pairs, vec![(a, e), (b, e)]);
+
+        let root = &tree.nodes[0];
+        assert!(root.children.is_some());
+        assert_eq!(root.items.iter().map(|index| tree.proxies[*index].entity).collect::<Vec<_>>(), vec![e]);
+        for child in root.children.unwrap() {
+            assert_eq!(tree.nodes[child].items.len(), 1);
+        }
+    }
+}
@@ -0,0 +1,205 @@
+use bevy::{prelude::*, utils::HashMap};
+use prima::prelude::*;
+
+use super::{BroadPhase, Proxy, ProxyList};
+use crate::components::CollisionGroups;
+
+pub const DEFAULT_CELL_SIZE: f32 = 4.0;
+
+/// A uniform grid, hashed so it does not need to know the size of the world up front. Works best when objects are roughly
+/// the same size as a cell and evenly spread out.
+#[derive(Debug, Clone)]
+pub struct SpatialHashGrid {
+    cell_size: f32,
+    proxies: ProxyList,
+    /// The proxies touching each cell, kept up to date as they are inserted, moved and removed.
+    cells: HashMap<(i32, i32), Vec<usize>>,
+}
+
+impl SpatialHashGrid {
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            proxies: ProxyList::new(),
+            cells: HashMap::default(),
+        }
+    }
+
+    pub fn cell_size(&self) -> f32 {
+        self.cell_size
+    }
+
+    fn cell(&self, point: Vec2) -> (i32, i32) {
+        (
+            (point.x / self.cell_size).floor() as i32,
+            (point.y / self.cell_size).floor() as i32,
+        )
+    }
+
+    /// The first and last cells covered by the bounds.
+    fn span(&self, min: Vec2, max: Vec2) -> ((i32, i32), (i32, i32)) {
+        (self.cell(min), self.cell(max))
+    }
+
+    fn file(&mut self, index: usize, ((x0, y0), (x1, y1)): ((i32, i32), (i32, i32))) {
+        for x in x0..=x1 {
+            for y in y0..=y1 {
+                self.cells.entry((x, y)).or_default().push(index);
+            }
+        }
+    }
+
+    fn unfile(&mut self, index: usize, ((x0, y0), (x1, y1)): ((i32, i32), (i32, i32))) {
+        for x in x0..=x1 {
+            for y in y0..=y1 {
+                if let Some(indices) = self.cells.get_mut(&(x, y)) {
+                    indices.retain(|i| *i != index);
+                    if indices.is_empty() {
+                        self.cells.remove(&(x, y));
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Default for SpatialHashGrid {
+    fn default() -> Self {
+        Self::new(DEFAULT_CELL_SIZE)
+    }
+}
+
+impl BroadPhase for SpatialHashGrid {
+    fn insert(&mut self, entity: Entity, bounds: Aabr<f32>, groups: CollisionGroups) {
+        let proxy = Proxy::new(entity, bounds, groups);
+        let span = self.span(proxy.min, proxy.max);
+        let (index, previous) = self.proxies.insert(proxy);
+        // Most updates stay inside the same cells, so there is nothing to move.
+        if let Some(previous) = previous {
+            let previous = self.span(previous.min, previous.max);
+            if previous == span {
+                return;
+            }
+            self.unfile(index, previous);
+        }
+        self.file(index, span);
+    }
+
+    fn update(&mut self, entity: Entity, bounds: Aabr<f32>, groups: CollisionGroups) {
+        self.insert(entity, bounds, groups);
+    }
+
+    fn remove(&mut self, entity: Entity) {
+        if let Some((index, proxy)) = self.proxies.remove(entity) {
+            self.unfile(index, self.span(proxy.min, proxy.max));
+            // The last proxy was moved into the gap, so its cells need to know where it went.
+            if let Some(moved) = self.proxies.get(index) {
+                let last = self.proxies.len();
+                let ((x0, y0), (x1, y1)) = self.span(moved.min, moved.max);
+                for x in x0..=x1 {
+                    for y in y0..=y1 {
+                        if let Some(indices) = self.cells.get_mut(&(x, y)) {
+                            indices.iter_mut().filter(|i| **i == last).for_each(|i| *i = index);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn pairs(&mut self) -> Vec<(Entity, Entity)> {
+        let mut pairs = Vec::new();
+        for (cell, indices) in self.cells.iter() {
+            for (i, index_a) in indices.iter().enumerate() {
+                for index_b in indices[i + 1..].iter() {
+                    let a = &self.proxies[*index_a];
+                    let b = &self.proxies[*index_b];
+                    if !a.interacts(b) || !a.overlaps(b) {
+                        continue;
+                    }
+                    // A pair can share several cells, so only report it from the cell holding the corner of their overlap.
+                    if self.cell(a.min.max(b.min)) == *cell {
+                        pairs.push((a.entity, b.entity));
+                    }
+                }
+            }
+        }
+        pairs
+    }
+
+    fn query(&self, region: &Aabr<f32>, groups: CollisionGroups) -> Vec<Entity> {
+        let region_min = Vec2::new(region.min.x, region.min.y);
+        let ((x0, y0), (x1, y1)) = self.span(region_min, Vec2::new(region.max.x, region.max.y));
+        let visit = |cell: &(i32, i32), indices: &Vec<usize>, entities: &mut Vec<Entity>| {
+            for index in indices.iter() {
+                let proxy = &self.proxies[*index];
+                // Like pairs, a proxy is only reported from the cell holding the corner of its overlap with the region.
+                if groups.interacts_with(&proxy.groups)
+                    && proxy.overlaps_region(region)
+                    && self.cell(proxy.min.max(region_min)) == *cell
+                {
+                    entities.push(proxy.entity);
+                }
+            }
+        };
+
+        let mut entities = Vec::new();
+        let width = (x1 as i64 - x0 as i64 + 1) as usize;
+        let height = (y1 as i64 - y0 as i64 + 1) as usize;
+        if width.saturating_mul(height) > self.cells.len() {
+            // A big region covers more cells than are in use, so it is quicker to go through the ones that are.
+            for (cell, indices) in self.cells.iter() {
+                if (x0..=x1).contains(&cell.0) && (y0..=y1).contains(&cell.1) {
+                    visit(cell, indices, &mut entities);
+                }
+            }
+        } else {
+            for x in x0..=x1 {
+                for y in y0..=y1 {
+                    if let Some(indices) = self.cells.get(&(x, y)) {
+                        visit(&(x, y), indices, &mut entities);
+                    }
+                }
+            }
+        }
+        entities
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bounds(min: (f32, f32), max: (f32, f32)) -> Aabr<f32> {
+        Aabr::new(Point::new(min.0, min.1), Point::new(max.0, max.1))
+    }
+
+    #[test]
+    fn proxies_straddling_cells_are_paired_once() {
+        let [a, b, c, d] = [0, 1, 2, 3].map(Entity::from_raw);
+        let mut grid = SpatialHashGrid::new(1.0);
+        // Covers the corner of four cells.
+        grid.insert(a, bounds((0.5, 0.5), (1.5, 1.5)), CollisionGroups::new(1, 1));
+        grid.insert(b, bounds((0.2, 0.2), (0.7, 0.7)), CollisionGroups::new(1, 1));
+        grid.insert(c, bounds((1.3, 1.3), (1.8, 1.8)), CollisionGroups::new(1, 1));
+        // Shares nine cells with 'a'.
+        grid.insert(d, bounds((-0.5, -0.5), (2.5, 2.5)), CollisionGroups::new(1, 1));
+
+        let mut pairs: Vec<_> = grid.pairs().into_iter().map(|(x, y)| (x.min(y), x.max(y))).collect();
+        pairs.sort();
+        assert_eq!(pairs, vec![(a, b), (a, c), (a, d), (b, d), (c, d)]);
+    }
+
+    #[test]
+    fn removed_proxies_are_not_paired() {
+        let [a, b, c] = [0, 1, 2].map(Entity::from_raw);
+        let mut grid = SpatialHashGrid::new(1.0);
+        grid.insert(a, bounds((0.0, 0.0), (0.5, 0.5)), CollisionGroups::new(1, 1));
+        grid.insert(b, bounds((0.2, 0.2), (0.7, 0.7)), CollisionGroups::new(1, 1));
+        grid.insert(c, bounds((5.0, 5.0), (5.5, 5.5)), CollisionGroups::new(1, 1));
+        grid.remove(a);
+        // 'c' took the removed slot, and has to be found there.
+        grid.update(c, bounds((0.4, 0.4), (0.9, 0.9)), CollisionGroups::new(1, 1));
+        assert_eq!(grid.pairs().len(), 1);
+    }
+}
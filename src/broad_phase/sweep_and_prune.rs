@@ -0,0 +1,191 @@
+use bevy::{prelude::*, utils::HashMap};
+use prima::prelude::*;
+
+use super::{BroadPhase, Proxy};
+use crate::components::CollisionGroups;
+
+/// Sort-and-sweep broad phase. Proxies are kept sorted along the x axis between frames, so a scene where
+/// bodies only move a little each frame costs a handful of swaps to re-sort rather than a full sort.
+#[derive(Debug, Clone, Default)]
+pub struct SweepAndPrune {
+    proxies: Vec<Proxy>,
+    lookup: HashMap<Entity, usize>,
+    dirty: bool,
+    /// How many proxies have been added since the last sweep. They are not sorted yet.
+    inserted: usize,
+    /// Set when a proxy is added or moved, until the next sweep sorts them again.
+    unsorted: bool,
+}
+
+impl SweepAndPrune {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl BroadPhase for SweepAndPrune {
+    fn insert(&mut self, entity: Entity, bounds: Aabr<f32>, groups: CollisionGroups) {
+        if self.lookup.contains_key(&entity) {
+            self.update(entity, bounds, groups);
+            return;
+        }
+        self.lookup.insert(entity, self.proxies.len());
+        self.proxies.push(Proxy::new(entity, bounds, groups));
+        self.inserted += 1;
+        self.unsorted = true;
+    }
+
+    fn update(&mut self, entity: Entity, bounds: Aabr<f32>, groups: CollisionGroups) {
+        if let Some(&index) = self.lookup.get(&entity) {
+            self.proxies[index] = Proxy::new(entity, bounds, groups);
+            self.unsorted = true;
+        } else {
+            self.insert(entity, bounds, groups);
+        }
+    }
+
+    fn remove(&mut self, entity: Entity) {
+        if self.lookup.remove(&entity).is_some() {
+            self.dirty = true;
+        }
+    }
+
+    fn pairs(&mut self) -> Vec<(Entity, Entity)> {
+        if self.dirty {
+            // Only keep proxies the lookup still points at, in case an entity was removed and re-inserted.
+            let lookup = &self.lookup;
+            let mut index = 0;
+            self.proxies.retain(|proxy| {
+                let keep = lookup.get(&proxy.entity) == Some(&index);
+                index += 1;
+                keep
+            });
+            self.dirty = false;
+        }
+
+        if self.inserted * 4 > self.proxies.len() {
+            // Lots of new proxies, such as on the first sweep, would each have a long way to travel.
+            self.proxies
+                .sort_unstable_by(|a, b| a.min.x.partial_cmp(&b.min.x).unwrap_or(std::cmp::Ordering::Equal));
+        } else {
+            // Insertion sort is close to linear on an almost sorted list, which is what we get from a coherent scene.
+            for i in 1..self.proxies.len() {
+                let mut j = i;
+                while j > 0 && self.proxies[j - 1].min.x > self.proxies[j].min.x {
+                    self.proxies.swap(j - 1, j);
+                    j -= 1;
+                }
+            }
+        }
+        self.inserted = 0;
+        self.unsorted = false;
+
+        self.lookup.clear();
+        for (index, proxy) in self.proxies.iter().enumerate() {
+            self.lookup.insert(proxy.entity, index);
+        }
+
+        let mut pairs = Vec::new();
+        for (i, a) in self.proxies.iter().enumerate() {
+            for b in self.proxies[i + 1..].iter() {
+                // Everything past this point starts after 'a' ends.
+                if b.min.x > a.max.x {
+                    break;
+                }
+                if a.interacts(b) && a.overlaps(b) {
+                    pairs.push((a.entity, b.entity));
+                }
+            }
+        }
+        pairs
+    }
+
+    fn query(&self, region: &Aabr<f32>, groups: CollisionGroups) -> Vec<Entity> {
+        let mut entities = Vec::new();
+        for (index, proxy) in self.proxies.iter().enumerate() {
+            // Still sorted from the last sweep, so nothing further along can reach the region.
+            if !self.unsorted && proxy.min.x > region.max.x {
+                break;
+            }
+            if self.lookup.get(&proxy.entity) != Some(&index) {
+                continue;
+            }
+            if groups.interacts_with(&proxy.groups) && proxy.overlaps_region(region) {
+                entities.push(proxy.entity);
+            }
+        }
+        entities
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bounds(x: f32, y: f32) -> Aabr<f32> {
+        Aabr::new(Point::new(x, y), Point::new(x + 1.0, y + 1.0))
+    }
+
+    /// Puts pairs in a fixed order, as the sweep reports them in whatever order the proxies are sorted.
+    fn sorted(pairs: Vec<(Entity, Entity)>) -> Vec<(Entity, Entity)> {
+        let mut pairs: Vec<_> = pairs.into_iter().map(|(a, b)| (a.min(b), a.max(b))).collect();
+        pairs.sort();
+        pairs
+    }
+
+    #[test]
+    fn finds_overlaps_on_both_axes() {
+        let (a, b, c, d) = (Entity::from_raw(0), Entity::from_raw(1), Entity::from_raw(2), Entity::from_raw(3));
+        let mut sap = SweepAndPrune::new();
+        sap.insert(a, bounds(0.0, 0.0), CollisionGroups::new(1, 1));
+        sap.insert(b, bounds(0.5, 0.5), CollisionGroups::new(1, 1));
+        // Overlaps 'a' along x only.
+        sap.insert(c, bounds(0.5, 5.0), CollisionGroups::new(1, 1));
+        // Overlaps 'b', but neither accepts the other's groups.
+        sap.insert(d, bounds(1.0, 1.0), CollisionGroups::new(2, 2));
+        assert_eq!(sorted(sap.pairs()), vec![(a, b)]);
+    }
+
+    #[test]
+    fn keeps_up_with_moving_and_removed_proxies() {
+        let (a, b, c) = (Entity::from_raw(0), Entity::from_raw(1), Entity::from_raw(2));
+        let mut sap = SweepAndPrune::new();
+        sap.insert(a, bounds(0.0, 0.0), CollisionGroups::new(1, 1));
+        sap.insert(b, bounds(5.0, 0.0), CollisionGroups::new(1, 1));
+        sap.insert(c, bounds(10.0, 0.0), CollisionGroups::new(1, 1));
+        assert!(sap.pairs().is_empty());
+
+        // 'c' passes 'b' and lands on 'a', which only the insertion sort has to put right.
+        sap.update(c, bounds(-0.5, 0.0), CollisionGroups::new(1, 1));
+        assert_eq!(sorted(sap.pairs()), vec![(a, c)]);
+
+        sap.remove(a);
+        assert!(sap.pairs().is_empty());
+        assert_eq!(sap.proxies.len(), 2);
+
+        // Removed and added back before the next sweep.
+        sap.remove(b);
+        sap.insert(b, bounds(0.0, 0.0), CollisionGroups::new(1, 1));
+        assert_eq!(sorted(sap.pairs()), vec![(b, c)]);
+        assert_eq!(sap.proxies.len(), 2);
+    }
+    #[test]
+    fn queries_only_stop_early_while_sorted() {
+        let (a, b) = (Entity::from_raw(0), Entity::from_raw(1));
+        let groups = CollisionGroups::new(1, 1);
+        let mut sap = SweepAndPrune::new();
+        sap.insert(a, bounds(0.0, 0.0), groups);
+        sap.insert(b, bounds(5.0, 0.0), groups);
+        sap.pairs();
+        assert_eq!(sap.query(&Aabr::new(Point::new(4.5, 0.0), Point::new(6.0, 1.0)), groups), vec![b]);
+
+        // 'b' moves behind 'a' but stays after it in the list until the next sweep, so the query has to look past 'a'.
+        sap.update(b, bounds(-10.0, 0.0), groups);
+        let region = Aabr::new(Point::new(-10.5, 0.0), Point::new(-9.0, 1.0));
+        assert_eq!(sap.query(&region, groups), vec![b]);
+
+        sap.pairs();
+        assert_eq!(sap.proxies[0].entity, b);
+        assert_eq!(sap.query(&region, groups), vec![b]);
+    }
+}
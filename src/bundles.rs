@@ -4,19 +4,57 @@ use crate::{components::*};
 #[derive(Bundle, Default)]
 pub struct RigidBodyBundle {
     pub rb: RigidBody,
+    pub body_type: BodyType,
     pub collider: Collider,
     pub mass: Mass,
+    pub inertia: Inertia,
+    pub center_of_mass: CenterOfMass,
     pub velocity: Velocity,
     pub forces: Forces,
+    pub sleep: Sleep,
     pub properties: Handle<PhysicsMaterial>,
     pub render: ColliderRender,
 }
 
-#[derive(Bundle, Default)]
+#[derive(Bundle)]
 pub struct StaticRigidBodyBundle {
     pub rb: RigidBody,
+    pub body_type: BodyType,
     pub collider: Collider,
     pub properties: Handle<PhysicsMaterial>,
     pub render: ColliderRender,
 }
 
+impl Default for StaticRigidBodyBundle {
+    fn default() -> Self {
+        Self {
+            rb: RigidBody::default(),
+            body_type: BodyType::Static,
+            collider: Collider::default(),
+            properties: Handle::default(),
+            render: ColliderRender::default(),
+        }
+    }
+}
+
+
+#[derive(Bundle)]
+pub struct SensorBundle {
+    pub rb: RigidBody,
+    pub body_type: BodyType,
+    pub collider: Collider,
+    pub sensor: Sensor,
+    pub render: ColliderRender,
+}
+
+impl Default for SensorBundle {
+    fn default() -> Self {
+        Self {
+            rb: RigidBody::default(),
+            body_type: BodyType::KinematicPosition,
+            collider: Collider::default(),
+            sensor: Sensor,
+            render: ColliderRender::default(),
+        }
+    }
+}
@@ -0,0 +1,225 @@
+use bevy::math::Vec2;
+use prima::prelude::*;
+
+use crate::{
+    contact::{edge_normal, Contact, Penetration},
+    convex_hull, AbstractShape, ShapeWrapper,
+};
+
+/// Where a ray first meets a circle, as a distance along the ray and the surface normal there. A ray starting inside
+/// hits straight away, facing back along itself.
+pub fn ray_circle(center: Vec2, radius: f32, origin: Vec2, direction: Vec2, max: f32) -> Option<(f32, Vec2)> {
+    let offset = origin - center;
+    let c = offset.length_squared() - radius * radius;
+    if c <= 0.0 {
+        return Some((0.0, -direction));
+    }
+    let b = offset.dot(direction);
+    if b > 0.0 {
+        return None;
+    }
+    let discriminant = b * b - c;
+    if discriminant < 0.0 {
+        return None;
+    }
+    let t = -b - discriminant.sqrt();
+    if t > max {
+        return None;
+    }
+    Some((t, (origin + direction * t - center).normalize_or_zero()))
+}
+
+/// Where a ray first meets a convex polygon wound anti-clockwise. Each edge cuts the ray down, and whatever is left
+/// starts at the hit.
+pub fn ray_polygon(vertices: &[Vec2], origin: Vec2, direction: Vec2, max: f32) -> Option<(f32, Vec2)> {
+    let mut enter = 0.0;
+    let mut exit = max;
+    let mut normal = -direction;
+    for i in 0..vertices.len() {
+        let start = vertices[i];
+        let edge = edge_normal(start, vertices[(i + 1) % vertices.len()]);
+        let towards = edge.dot(direction);
+        let distance = edge.dot(start - origin);
+        if towards.abs() <= f32::EPSILON {
+            // Running alongside the edge, so it either misses completely or the edge does not matter.
+            if distance < 0.0 {
+                return None;
+            }
+            continue;
+        }
+        let t = distance / towards;
+        if towards < 0.0 {
+            if t > enter {
+                enter = t;
+                normal = edge;
+            }
+        } else if t < exit {
+            exit = t;
+        }
+        if enter > exit {
+            return None;
+        }
+    }
+    Some((enter, normal))
+}
+
+/// Where a ray crosses a bare line, with the normal facing back towards the ray.
+pub fn ray_segment(start: Vec2, end: Vec2, origin: Vec2, direction: Vec2, max: f32) -> Option<(f32, Vec2)> {
+    let edge = end - start;
+    let denominator = direction.perp_dot(edge);
+    if denominator.abs() <= f32::EPSILON {
+        return None;
+    }
+    let offset = start - origin;
+    let t = offset.perp_dot(edge) / denominator;
+    let u = offset.perp_dot(direction) / denominator;
+    if t < 0.0 || t > max || !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let normal = edge_normal(start, end);
+    Some((t, if normal.dot(direction) > 0.0 { -normal } else { normal }))
+}
+
+/// Where a ray first meets a line with rounded ends. That is the nearest of the two end circles and the box between them.
+pub fn ray_rounded_segment(
+    start: Vec2,
+    end: Vec2,
+    radius: f32,
+    origin: Vec2,
+    direction: Vec2,
+    max: f32,
+) -> Option<(f32, Vec2)> {
+    let side = (end - start).normalize_or_zero();
+    let left = Vec2::new(-side.y, side.x) * radius;
+    let body = [start - left, end - left, end + left, start + left];
+    [
+        ray_circle(start, radius, origin, direction, max),
+        ray_circle(end, radius, origin, direction, max),
+        if side == Vec2::ZERO { None } else { ray_polygon(&body, origin, direction, max) },
+    ]
+    .into_iter()
+    .flatten()
+    .min_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal))
+}
+
+/// Where a ray first meets a convex core rounded out by a radius. A single point makes a circle and two make a segment,
+/// so this covers every shape once it has been flattened down to its outline.
+pub fn ray_rounded_polygon(
+    core: &[Vec2],
+    radius: f32,
+    origin: Vec2,
+    direction: Vec2,
+    max: f32,
+) -> Option<(f32, Vec2)> {
+    let flat = radius <= f32::EPSILON;
+    match core.len() {
+        0 => None,
+        1 => ray_circle(core[0], radius, origin, direction, max),
+        2 if flat => ray_segment(core[0], core[1], origin, direction, max),
+        2 => ray_rounded_segment(core[0], core[1], radius, origin, direction, max),
+        _ if flat => ray_polygon(core, origin, direction, max),
+        len => {
+            // The rounded polygon is the core with a capsule along every edge.
+            let edges = (0..len).map(|i| ray_rounded_segment(core[i], core[(i + 1) % len], radius, origin, direction, max));
+            std::iter::once(ray_polygon(core, origin, direction, max))
+                .chain(edges)
+                .flatten()
+                .min_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal))
+        }
+    }
+}
+
+/// Bounds covering everything a rectangle passes over as it is moved along a translation.
+pub fn swept_bounds(bounds: &Aabr<f32>, translation: Vec2) -> Aabr<f32> {
+    Aabr::new(
+        Point::new(bounds.min.x + translation.x.min(0.0), bounds.min.y + translation.y.min(0.0)),
+        Point::new(bounds.max.x + translation.x.max(0.0), bounds.max.y + translation.y.max(0.0)),
+    )
+}
+
+/// Sweeps a shape along a translation, returning how far along it first touches the target (from 0.0 to 1.0) and the
+/// contact at that point. The target is grown by the moving shape, turning the sweep into a single ray cast, so nothing
+/// is too thin or too fast to be found.
+pub fn cast_shape(
+    shape: &AbstractShape,
+    position: Vec2,
+    rotation: f32,
+    translation: Vec2,
+    target: &ShapeWrapper,
+) -> Option<(f32, Contact)> {
+    let mover = shape.wrap(Point::new(position.x, position.y), rotation);
+    if let Some(contact) = mover.contact(target) {
+        return Some((0.0, contact));
+    }
+    let length = translation.length();
+    if length <= f32::EPSILON {
+        return None;
+    }
+
+    // Every point the target could be at, relative to the mover. Where the translation first lands in there, they touch.
+    let (a, a_radius) = mover.outline();
+    let (b, b_radius) = target.outline();
+    let difference: Vec<Vec2> = b.iter().flat_map(|b| a.iter().map(move |a| *b - *a)).collect();
+    let (distance, normal) = ray_rounded_polygon(
+        &convex_hull(&difference),
+        a_radius + b_radius,
+        Vec2::ZERO,
+        translation / length,
+        length,
+    )?;
+
+    // The surface faces back at the mover, but the contact points away from it.
+    let normal = -normal;
+    let toi = distance / length;
+    let support = a
+        .iter()
+        .copied()
+        .max_by(|x, y| x.dot(normal).partial_cmp(&y.dot(normal)).unwrap_or(std::cmp::Ordering::Equal))
+        .unwrap_or(position);
+    let point = support + translation * toi + normal * a_radius;
+    Some((
+        toi,
+        Contact {
+            normal,
+            points: vec![Penetration { point, depth: 0.0, id: 0 }],
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rays_enter_polygons_through_the_nearest_edge() {
+        let square = [Vec2::new(1.0, -1.0), Vec2::new(3.0, -1.0), Vec2::new(3.0, 1.0), Vec2::new(1.0, 1.0)];
+        assert_eq!(ray_polygon(&square, Vec2::ZERO, Vec2::X, 10.0), Some((1.0, -Vec2::X)));
+        assert_eq!(ray_polygon(&square, Vec2::ZERO, Vec2::X, 0.5), None);
+        assert_eq!(ray_polygon(&square, Vec2::new(0.0, 2.0), Vec2::X, 10.0), None);
+        // Starting inside hits straight away.
+        assert_eq!(ray_polygon(&square, Vec2::new(2.0, 0.0), Vec2::X, 10.0), Some((0.0, -Vec2::X)));
+    }
+
+    #[test]
+    fn rays_meet_circles_and_rounded_ends() {
+        assert_eq!(ray_circle(Vec2::new(3.0, 0.0), 1.0, Vec2::ZERO, Vec2::X, 10.0), Some((2.0, -Vec2::X)));
+        assert_eq!(ray_circle(Vec2::new(3.0, 0.0), 1.0, Vec2::ZERO, -Vec2::X, 10.0), None);
+
+        let hit = ray_rounded_segment(Vec2::new(2.0, 0.0), Vec2::new(4.0, 0.0), 0.5, Vec2::ZERO, Vec2::X, 10.0);
+        assert_eq!(hit, Some((1.5, -Vec2::X)));
+        let hit = ray_rounded_segment(Vec2::new(2.0, 0.0), Vec2::new(4.0, 0.0), 0.5, Vec2::new(3.0, 2.0), -Vec2::Y, 10.0);
+        assert_eq!(hit, Some((1.5, Vec2::Y)));
+    }
+
+    #[test]
+    fn shapes_stop_where_they_first_touch() {
+        let wall = AbstractShape::Aabr { width: 2.0, height: 2.0 }.wrap(Point::new(5.0, 0.0), 0.0);
+        let ball = AbstractShape::Circle { radius: 0.5 };
+
+        let (toi, contact) = cast_shape(&ball, Vec2::ZERO, 0.0, Vec2::new(10.0, 0.0), &wall).unwrap();
+        assert!((toi - 0.35).abs() < 1e-3);
+        assert!((contact.normal - Vec2::X).length() < 1e-3);
+        assert!(cast_shape(&ball, Vec2::ZERO, 0.0, Vec2::new(0.0, 10.0), &wall).is_none());
+        assert!(cast_shape(&ball, Vec2::ZERO, 0.0, Vec2::new(3.0, 0.0), &wall).is_none());
+    }
+}
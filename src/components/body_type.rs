@@ -0,0 +1,36 @@
+use bevy::prelude::*;
+use bevy_inspector_egui::Inspectable;
+
+/// How a body takes part in the simulation. Bodies without one are treated as dynamic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Component, Inspectable)]
+pub enum BodyType {
+    /// Moved by gravity, forces and collisions.
+    Dynamic,
+    /// Never moves. Static bodies are not even checked against each other.
+    Static,
+    /// Moves at whatever its [super::Velocity] says, ignoring gravity, forces and collisions.
+    KinematicVelocity,
+    /// Moved by setting its [super::RigidBody] position directly. Its [super::Velocity], if it has one, is worked out from
+    /// how far it was moved so it can carry dynamic bodies along with it.
+    KinematicPosition,
+}
+
+impl BodyType {
+    pub fn is_dynamic(self) -> bool {
+        self == BodyType::Dynamic
+    }
+
+    pub fn is_static(self) -> bool {
+        self == BodyType::Static
+    }
+
+    pub fn is_kinematic(self) -> bool {
+        matches!(self, BodyType::KinematicVelocity | BodyType::KinematicPosition)
+    }
+}
+
+impl Default for BodyType {
+    fn default() -> Self {
+        BodyType::Dynamic
+    }
+}
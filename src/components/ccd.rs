@@ -0,0 +1,6 @@
+use bevy::prelude::*;
+
+/// Sweeps a body from where it was at the start of each step to where it ended up, stopping it at the first collider in
+/// the way rather than letting it pass straight through. Only worth it for small, fast bodies such as bullets.
+#[derive(Debug, Clone, Copy, Default, Component)]
+pub struct Ccd;
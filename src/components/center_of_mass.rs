@@ -0,0 +1,20 @@
+use bevy::prelude::*;
+use bevy_inspector_egui::Inspectable;
+
+/// Where the body balances, relative to its position and before rotation. Bodies spin about this point. It is worked out
+/// from the body's colliders, so only compound bodies will usually have one away from the origin.
+#[derive(Debug, Clone, Copy, Default, Component, Inspectable)]
+pub struct CenterOfMass {
+    #[inspectable(read_only)]
+    value: Vec2,
+}
+
+impl CenterOfMass {
+    pub fn raw(self) -> Vec2 {
+        self.value
+    }
+
+    pub(crate) fn set(&mut self, center: Vec2) {
+        self.value = center;
+    }
+}
@@ -3,12 +3,41 @@ use bevy::prelude::*;
 use bevy_inspector_egui::Inspectable;
 use prima::prelude::*;
 
-pub const DEFAULT_LAYER: u8 = 0b0000_0001;
+pub const DEFAULT_GROUP: u32 = 0b0000_0001;
+pub const ALL_GROUPS: u32 = u32::MAX;
 
-#[derive(Debug, Clone, Copy, Component, Inspectable)]
+/// Decides which colliders are allowed to touch. Two colliders only collide if each one's memberships overlap the
+/// other's filter, so both sides have to agree. A bullet that filters for enemies will never hit an enemy that does not
+/// filter for bullets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Inspectable)]
+pub struct CollisionGroups {
+    /// The groups this collider belongs to.
+    pub memberships: u32,
+    /// The groups this collider is allowed to collide with.
+    pub filter: u32,
+}
+
+impl CollisionGroups {
+    pub fn new(memberships: u32, filter: u32) -> Self {
+        Self { memberships, filter }
+    }
+
+    /// True if both colliders accept each other.
+    pub fn interacts_with(&self, other: &CollisionGroups) -> bool {
+        self.memberships & other.filter != 0 && other.memberships & self.filter != 0
+    }
+}
+
+impl Default for CollisionGroups {
+    fn default() -> Self {
+        Self::new(DEFAULT_GROUP, ALL_GROUPS)
+    }
+}
+
+#[derive(Debug, Clone, Component, Inspectable)]
 pub struct Collider {
     pub shape: AbstractShape,
-    pub layer: u8,
+    pub groups: CollisionGroups,
 }
 
 impl Collider {
@@ -17,7 +46,7 @@ impl Collider {
             shape: AbstractShape::Aabr {
                 width: size, height: size,
             },
-            layer: DEFAULT_LAYER,
+            groups: CollisionGroups::default(),
         }
     }
 
@@ -26,52 +55,60 @@ impl Collider {
             shape: AbstractShape::Aabr {
                 width, height,
             },
-            layer: DEFAULT_LAYER,
+            groups: CollisionGroups::default(),
         }
     }
 
     pub fn circle(radius: f32) -> Self {
         Self {
             shape: AbstractShape::Circle { radius },
-            layer: DEFAULT_LAYER,
+            groups: CollisionGroups::default(),
         }
     }
 
     pub fn line(start: Point<f32>, end: Point<f32>) -> Self {
         Self {
-            shape: AbstractShape::Line {
-                start: Vec2::new(start.x, start.y),
-                end: Vec2::new(end.x, end.y),
-            },
-            layer: DEFAULT_LAYER,
+            shape: AbstractShape::line(Vec2::new(start.x, start.y), Vec2::new(end.x, end.y)),
+            groups: CollisionGroups::default(),
         }
     }
 
-    pub fn with_layers(mut self, layers: u8) -> Self {
-        self.layer = layers;
-        self
+    pub fn thick_line(start: Point<f32>, end: Point<f32>, thickness: f32) -> Self {
+        Self {
+            shape: AbstractShape::thick_line(Vec2::new(start.x, start.y), Vec2::new(end.x, end.y), thickness),
+            groups: CollisionGroups::default(),
+        }
     }
 
-    pub fn global_aabr(&self, rb: &RigidBody) -> Aabr<f32> {
-        match self.shape {
-            AbstractShape::Circle { radius } => Circle::new(rb.position(), radius).bounding_rect(),
-            AbstractShape::Aabr { width, height } => {
-                Aabr::from_point(rb.position(), width, height)
-            }
-            AbstractShape::Line { start: a, end: b } => {
-                Aabr::new(Point::new(a.x, a.y), Point::new(b.x, b.y))
-            }
+    pub fn capsule(half_height: f32, radius: f32) -> Self {
+        Self {
+            shape: AbstractShape::capsule(half_height, radius),
+            groups: CollisionGroups::default(),
         }
     }
 
-    pub fn as_shape(&self, rb: &RigidBody) -> Box<dyn Shape<f32>> {
-        match self.shape {
-            AbstractShape::Circle { radius } => Box::new(Circle::new(rb.position(), radius)),
-            AbstractShape::Aabr { width, height } => {
-                Box::new(Aabr::from_point(rb.position(), width, height))
-            }
-            AbstractShape::Line { start: _, end: _ } => todo!(),
-        }
+    /// A convex polygon around the body's position. The points can be in any order, as the hull is taken. Returns None if
+    /// they do not enclose any area.
+    pub fn polygon(points: &[Vec2]) -> Option<Self> {
+        Some(Self {
+            shape: AbstractShape::polygon(points)?,
+            groups: CollisionGroups::default(),
+        })
+    }
+
+    /// Puts the collider in the given groups, and only lets it collide with those same groups.
+    pub fn with_layers(mut self, layers: u32) -> Self {
+        self.groups = CollisionGroups::new(layers, layers);
+        self
+    }
+
+    pub fn with_groups(mut self, groups: CollisionGroups) -> Self {
+        self.groups = groups;
+        self
+    }
+
+    pub fn global_aabr(&self, rb: &RigidBody) -> Aabr<f32> {
+        self.shape.wrap(rb.position(), rb.applied_rotation()).bounding_rect()
     }
 }
 
@@ -79,7 +116,41 @@ impl Default for Collider {
     fn default() -> Self {
         Self {
             shape: AbstractShape::Circle { radius: 0.5 },
-            layer: DEFAULT_LAYER,
+            groups: CollisionGroups::default(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAYER: u32 = 0b01;
+    const ENEMY: u32 = 0b10;
+
+    #[test]
+    fn both_sides_have_to_accept_each_other() {
+        let player = CollisionGroups::new(PLAYER, ENEMY);
+        let enemy = CollisionGroups::new(ENEMY, PLAYER);
+        assert!(player.interacts_with(&enemy));
+        assert!(enemy.interacts_with(&player));
+
+        // Only one direction accepts, which is not enough from either side.
+        let ghost = CollisionGroups::new(ENEMY, 0);
+        assert!(!player.interacts_with(&ghost));
+        assert!(!ghost.interacts_with(&player));
+
+        // Players filter out other players.
+        assert!(!player.interacts_with(&player));
+    }
+
+    #[test]
+    fn layers_are_both_membership_and_filter() {
+        let a = Collider::default().with_layers(PLAYER | ENEMY).groups;
+        let b = Collider::default().with_layers(ENEMY).groups;
+        let c = Collider::default().with_layers(PLAYER).groups;
+        assert!(a.interacts_with(&b) && b.interacts_with(&a));
+        assert!(!b.interacts_with(&c) && !c.interacts_with(&b));
+        assert!(Collider::default().groups.interacts_with(&a));
+    }
+}
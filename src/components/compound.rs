@@ -0,0 +1,78 @@
+use crate::AbstractShape;
+use bevy::prelude::*;
+use bevy_inspector_egui::Inspectable;
+
+use super::CollisionGroups;
+
+/// A shape placed relative to its body.
+#[derive(Debug, Clone, Inspectable)]
+pub struct ColliderPart {
+    pub shape: AbstractShape,
+    /// Offset from the body's position, before the body is rotated.
+    pub offset: Vec2,
+    /// Rotation in radians, on top of the body's own.
+    pub rotation: f32,
+}
+
+/// Several shapes that move as one body. Each one collides on its own, and manifolds record which part was hit. Works
+/// alongside a plain [super::Collider], and child entities with a [ColliderOffset].
+#[derive(Debug, Clone, Default, Component, Inspectable)]
+pub struct CompoundCollider {
+    pub parts: Vec<ColliderPart>,
+    pub groups: CollisionGroups,
+}
+
+/// Attaches a child entity's [super::Collider] to the [super::RigidBody] on its parent, making the parent a compound
+/// body. The child should not have a rigid body of its own.
+#[derive(Debug, Clone, Copy, Default, Component, Inspectable)]
+pub struct ColliderOffset {
+    pub translation: Vec2,
+    /// Rotation in radians, on top of the body's own.
+    pub rotation: f32,
+}
+
+/// Which part of a compound body a contact is on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum PartId {
+    /// An index into [CompoundCollider::parts].
+    Index(usize),
+    /// A child entity with its own collider.
+    Child(Entity),
+}
+
+impl ColliderPart {
+    pub fn new(shape: AbstractShape, offset: Vec2, rotation: f32) -> Self {
+        Self { shape, offset, rotation }
+    }
+}
+
+impl Default for ColliderPart {
+    fn default() -> Self {
+        Self::new(AbstractShape::Circle { radius: 1.0 }, Vec2::ZERO, 0.0)
+    }
+}
+
+impl CompoundCollider {
+    pub fn new(parts: Vec<ColliderPart>) -> Self {
+        Self {
+            parts,
+            groups: CollisionGroups::default(),
+        }
+    }
+
+    pub fn with_part(mut self, part: ColliderPart) -> Self {
+        self.parts.push(part);
+        self
+    }
+
+    pub fn with_groups(mut self, groups: CollisionGroups) -> Self {
+        self.groups = groups;
+        self
+    }
+}
+
+impl ColliderOffset {
+    pub fn new(translation: Vec2, rotation: f32) -> Self {
+        Self { translation, rotation }
+    }
+}
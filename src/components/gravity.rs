@@ -0,0 +1,22 @@
+use bevy::prelude::*;
+use bevy_inspector_egui::Inspectable;
+
+/// Scales the global gravity applied to an entity. 0.0 ignores gravity, negative values fall "upwards".
+#[derive(Debug, Clone, Copy, Component, Inspectable)]
+pub struct GravityScale(f32);
+
+impl GravityScale {
+    pub fn new(scale: f32) -> Self {
+        Self(scale)
+    }
+
+    pub fn raw(self) -> f32 {
+        self.0
+    }
+}
+
+impl Default for GravityScale {
+    fn default() -> Self {
+        Self::new(1.0)
+    }
+}
@@ -1,15 +1,55 @@
 use bevy::prelude::*;
 use bevy_inspector_egui::Inspectable;
 
-#[derive(Debug, Clone, Copy, Default, Component, Inspectable)]
-pub struct Inertia(f32);
+/// The moment of inertia of an entity, or how hard it is to spin. 0.0 is treated as infinite. Automatic inertia is derived
+/// from the entity's collider and mass, while inertia made with [Inertia::new] is left alone.
+#[derive(Debug, Clone, Copy, Component, Inspectable)]
+pub struct Inertia {
+    value: f32,
+    #[inspectable(read_only)]
+    automatic: bool,
+}
 
 impl Inertia {
     pub fn new(inertia: f32) -> Self {
-        Self(inertia)
+        Self {
+            value: inertia,
+            automatic: false,
+        }
+    }
+
+    /// An inertia that will be worked out from the collider's shape and the entity's mass.
+    pub fn auto() -> Self {
+        Self {
+            value: 0.0,
+            automatic: true,
+        }
     }
 
     pub fn inv(&self) -> f32 {
-        1.0 / self.0
+        if self.value == 0.0 {
+            0.0
+        } else {
+            1.0 / self.value
+        }
+    }
+
+    pub fn raw(&self) -> f32 {
+        self.value
+    }
+
+    pub fn is_auto(&self) -> bool {
+        self.automatic
+    }
+
+    /// Updates an automatic inertia, leaving it automatic.
+    pub(crate) fn set_auto(&mut self, inertia: f32) {
+        self.value = inertia;
+    }
+}
+
+impl Default for Inertia {
+    fn default() -> Self {
+        Self::auto()
     }
-}
\ No newline at end of file
+}
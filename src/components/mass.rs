@@ -1,30 +1,58 @@
 use bevy::prelude::*;
 use bevy_inspector_egui::Inspectable;
 
-/// Stores the mass associated with an entity.
+/// The mass used by [Mass::auto] until it has been derived from the collider.
+pub const DEFAULT_MASS: f32 = 100.0;
+
+/// Stores the mass associated with an entity. Automatic masses are derived from the entity's collider and material density,
+/// while masses made with [Mass::new] are left alone.
 #[derive(Debug, Clone, Copy, Component, Inspectable)]
-pub struct Mass(f32);
+pub struct Mass {
+    value: f32,
+    #[inspectable(read_only)]
+    automatic: bool,
+}
 
 impl Mass {
     pub fn new(mass: f32) -> Self {
-        Self(mass)
+        Self {
+            value: mass,
+            automatic: false,
+        }
+    }
+
+    /// A mass that will be worked out from the collider's shape and the material's density.
+    pub fn auto() -> Self {
+        Self {
+            value: DEFAULT_MASS,
+            automatic: true,
+        }
     }
 
     pub fn inv(self) -> f32 {
-        if self.0 == 0.0 {
+        if self.value == 0.0 {
             0.0
         } else {
-            1.0 / self.0
+            1.0 / self.value
         }
     }
 
     pub fn raw(self) -> f32 {
-        self.0
+        self.value
+    }
+
+    pub fn is_auto(self) -> bool {
+        self.automatic
+    }
+
+    /// Updates an automatic mass, leaving it automatic.
+    pub(crate) fn set_auto(&mut self, mass: f32) {
+        self.value = mass;
     }
 }
 
 impl Default for Mass {
     fn default() -> Self {
-        Self::new(100.0)
+        Self::auto()
     }
-}
\ No newline at end of file
+}
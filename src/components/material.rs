@@ -1,6 +1,44 @@
 use bevy::reflect::TypeUuid;
 use bevy_inspector_egui::Inspectable;
 
+pub const DEFAULT_DENSITY: f32 = 1.0;
+
+/// How the coefficients of two materials are merged when they meet. If both materials ask for a different rule,
+/// the one furthest down this list wins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Inspectable)]
+pub enum CombineRule {
+    Average,
+    Min,
+    Multiply,
+    Max,
+}
+
+impl CombineRule {
+    pub fn combine(self, a: f32, b: f32) -> f32 {
+        match self {
+            CombineRule::Average => (a + b) / 2.0,
+            CombineRule::Min => a.min(b),
+            CombineRule::Multiply => a * b,
+            CombineRule::Max => a.max(b),
+        }
+    }
+
+    /// Picks the rule to use for two materials, falling back to `default` when neither material has an opinion.
+    pub fn pick(default: CombineRule, a: Option<CombineRule>, b: Option<CombineRule>) -> CombineRule {
+        match (a, b) {
+            (Some(a), Some(b)) => a.max(b),
+            (Some(rule), None) | (None, Some(rule)) => rule,
+            (None, None) => default,
+        }
+    }
+}
+
+impl Default for CombineRule {
+    fn default() -> Self {
+        CombineRule::Average
+    }
+}
+
 #[derive(Debug, Clone, Copy, TypeUuid, Inspectable)]
 #[uuid = "468845e7-5b30-4816-b3fd-22f1b4b73adc"]
 pub struct PhysicsMaterial {
@@ -8,11 +46,52 @@ pub struct PhysicsMaterial {
     /// 0.0 is a mushy material, 1.0 is a very bouncy material.
     #[inspectable(min = 0.0, max = 1.0)]
     pub restitution: f32,
+    /// Overrides the resolver's restitution combine rule when set.
+    pub restitution_combine: Option<CombineRule>,
+    /// Mass per unit of area, used to work out automatic masses.
+    #[inspectable(min = 0.0)]
+    pub density: f32,
+    /// Friction coefficient while at rest. Sliding only starts once the tangential push beats this.
+    #[inspectable(min = 0.0)]
+    pub static_friction: f32,
+    /// Friction coefficient while sliding.
+    #[inspectable(min = 0.0)]
+    pub dynamic_friction: f32,
+    /// Overrides the resolver's friction combine rule when set.
+    pub friction_combine: Option<CombineRule>,
 }
 
 impl PhysicsMaterial {
     pub fn new(restitution: f32) -> Self {
-        Self { restitution }
+        Self {
+            restitution,
+            restitution_combine: None,
+            density: DEFAULT_DENSITY,
+            static_friction: 0.5,
+            dynamic_friction: 0.3,
+            friction_combine: None,
+        }
+    }
+
+    pub fn with_friction(mut self, static_friction: f32, dynamic_friction: f32) -> Self {
+        self.static_friction = static_friction;
+        self.dynamic_friction = dynamic_friction;
+        self
+    }
+
+    pub fn with_restitution_combine(mut self, rule: CombineRule) -> Self {
+        self.restitution_combine = Some(rule);
+        self
+    }
+
+    pub fn with_friction_combine(mut self, rule: CombineRule) -> Self {
+        self.friction_combine = Some(rule);
+        self
+    }
+
+    pub fn with_density(mut self, density: f32) -> Self {
+        self.density = density;
+        self
     }
 
     pub fn bouncy() -> Self {
@@ -26,6 +105,30 @@ impl PhysicsMaterial {
 
 impl Default for PhysicsMaterial {
     fn default() -> Self {
-        Self { restitution: 0.5 }
+        Self::new(0.5)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn combine_rules() {
+        assert_eq!(CombineRule::Average.combine(0.2, 0.6), 0.4);
+        assert_eq!(CombineRule::Min.combine(0.2, 0.6), 0.2);
+        assert_eq!(CombineRule::Multiply.combine(0.5, 0.6), 0.3);
+        assert_eq!(CombineRule::Max.combine(0.2, 0.6), 0.6);
+    }
+
+    #[test]
+    fn materials_override_the_default_rule() {
+        use CombineRule::*;
+        assert_eq!(CombineRule::pick(Average, None, None), Average);
+        assert_eq!(CombineRule::pick(Average, Some(Min), None), Min);
+        assert_eq!(CombineRule::pick(Max, None, Some(Min)), Min);
+        // When both materials have a rule the one further down the list wins, whichever side it is on.
+        assert_eq!(CombineRule::pick(Average, Some(Max), Some(Min)), Max);
+        assert_eq!(CombineRule::pick(Average, Some(Min), Some(Multiply)), Multiply);
     }
 }
@@ -1,17 +1,31 @@
+mod body_type;
+mod ccd;
+mod center_of_mass;
 mod collider;
+mod compound;
 mod forces;
+mod gravity;
 mod inertia;
 mod mass;
 mod material;
 mod render;
 mod rigid_body;
+mod sensor;
+mod sleep;
 mod velocity;
 
+pub use body_type::BodyType;
+pub use ccd::Ccd;
+pub use center_of_mass::CenterOfMass;
 pub use collider::*;
+pub use compound::*;
 pub use forces::Forces;
+pub use gravity::GravityScale;
 pub use inertia::Inertia;
-pub use mass::Mass;
-pub use material::PhysicsMaterial;
+pub use mass::*;
+pub use material::*;
 pub use render::ColliderRender;
-pub use rigid_body::RigidBody;
+pub use rigid_body::*;
+pub use sensor::Sensor;
+pub use sleep::Sleep;
 pub use velocity::Velocity;
@@ -37,8 +37,29 @@ impl RigidBody {
         self.position.y += translation.y;
     }
 
+    /// Rotates the body by the given amount of radians, keeping the stored rotation in the range 0.0 -> 2.0.
+    pub fn rotate(&mut self, radians: f32) {
+        self.rotation = (self.rotation + radians / std::f32::consts::PI).rem_euclid(2.0);
+    }
+
+    /// Blends between this state and another, taking the short way round for rotation.
+    pub fn lerp(&self, other: &RigidBody, t: f32) -> RigidBody {
+        let mut delta = (other.rotation - self.rotation).rem_euclid(2.0);
+        if delta > 1.0 {
+            delta -= 2.0;
+        }
+        RigidBody {
+            position: self.position.lerp(other.position, t),
+            rotation: (self.rotation + delta * t).rem_euclid(2.0),
+        }
+    }
+
     /// The 'true' value of rotation with pi applied. 
     pub fn applied_rotation(&self) -> f32 {
         self.rotation * std::f32::consts::PI
     }
-}
\ No newline at end of file
+}
+
+/// The [RigidBody] as it was at the start of the last physics step, used to smooth rendering between steps.
+#[derive(Debug, Clone, Copy, Default, Component)]
+pub struct PreviousRigidBody(pub RigidBody);
@@ -0,0 +1,6 @@
+use bevy::prelude::*;
+
+/// Marks a collider as a sensor. Sensors report overlaps through the trigger events, but never push or get pushed by
+/// anything.
+#[derive(Debug, Clone, Copy, Default, Component)]
+pub struct Sensor;
@@ -0,0 +1,51 @@
+use bevy::prelude::*;
+use bevy_inspector_egui::Inspectable;
+
+/// Lets a body fall asleep once it has been still for long enough, skipping it until something disturbs it. Bodies that
+/// touch sleep and wake together, so a stack will never be left half asleep.
+#[derive(Debug, Clone, Copy, Default, Component, Inspectable)]
+pub struct Sleep {
+    #[inspectable(read_only)]
+    asleep: bool,
+    /// Seconds the body has been below the sleep thresholds.
+    #[inspectable(read_only)]
+    idle: f32,
+    /// Every body in a sleeping island points at the same one, so the island still holds together while asleep.
+    #[inspectable(ignore)]
+    island: Option<Entity>,
+}
+
+impl Sleep {
+    pub fn is_asleep(&self) -> bool {
+        self.asleep
+    }
+
+    pub fn idle_time(&self) -> f32 {
+        self.idle
+    }
+
+    /// Wakes the body straight away, and everything it was sleeping with at the end of the next step.
+    pub fn wake_up(&mut self) {
+        self.asleep = false;
+        self.idle = 0.0;
+    }
+
+    pub(crate) fn island(&self) -> Option<Entity> {
+        self.island
+    }
+
+    pub(crate) fn fall_asleep(&mut self, island: Entity) {
+        self.asleep = true;
+        self.island = Some(island);
+    }
+
+    pub(crate) fn stir(&mut self) {
+        self.asleep = false;
+        self.idle = 0.0;
+        self.island = None;
+    }
+
+    pub(crate) fn tick(&mut self, still: bool, dt: f32) {
+        self.idle = if still { self.idle + dt } else { 0.0 };
+    }
+}
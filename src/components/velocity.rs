@@ -5,6 +5,7 @@ use prima::prelude::*;
 #[derive(Debug, Clone, Component, Inspectable)]
 pub struct Velocity {
     linear: Vec2,
+    /// Angular velocity in radians per second.
     angular: f32,
 }
 
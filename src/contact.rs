@@ -0,0 +1,513 @@
+use bevy::math::Vec2;
+use prima::prelude::*;
+
+use crate::Obb;
+
+/// A single point where two shapes overlap.
+#[derive(Debug, Clone, Copy)]
+pub struct Penetration {
+    /// World space position of the point.
+    pub point: Vec2,
+    /// How far the shapes overlap at this point.
+    pub depth: f32,
+    /// Identifies the pair of features that made this point, so it can be matched up next step.
+    pub id: u32,
+}
+
+/// The raw output of a collision test, before it is turned into a [crate::pipeline::Manifold].
+#[derive(Debug, Clone)]
+pub struct Contact {
+    /// Points from the first shape towards the second.
+    pub normal: Vec2,
+    /// Up to two points of contact.
+    pub points: Vec<Penetration>,
+}
+
+impl Contact {
+    /// The deepest point of the contact.
+    pub fn deepest(&self) -> Option<&Penetration> {
+        self.points
+            .iter()
+            .max_by(|a, b| a.depth.partial_cmp(&b.depth).unwrap_or(std::cmp::Ordering::Equal))
+    }
+
+    /// The same contact seen from the other shape.
+    pub fn flipped(mut self) -> Self {
+        self.normal = -self.normal;
+        self
+    }
+
+    /// Squashes the contact down to prima's single point form, using the deepest point.
+    pub fn collision(&self) -> Option<Collision<f32>> {
+        self.deepest().map(|deepest| Collision {
+            normal: Vector::new(self.normal.x, self.normal.y),
+            depth: deepest.depth,
+            point: Point::new(deepest.point.x, deepest.point.y),
+        })
+    }
+}
+
+impl From<Collision<f32>> for Contact {
+    fn from(collision: Collision<f32>) -> Self {
+        Contact {
+            normal: Vec2::new(collision.normal.x, collision.normal.y).normalize_or_zero(),
+            points: vec![Penetration {
+                point: Vec2::new(collision.point.x, collision.point.y),
+                depth: collision.depth,
+                id: 0,
+            }],
+        }
+    }
+}
+
+/// The corners of an axis aligned rectangle, wound anti-clockwise.
+pub fn aabr_vertices(aabr: &Aabr<f32>) -> [Vec2; 4] {
+    [
+        Vec2::new(aabr.min.x, aabr.min.y),
+        Vec2::new(aabr.max.x, aabr.min.y),
+        Vec2::new(aabr.max.x, aabr.max.y),
+        Vec2::new(aabr.min.x, aabr.max.y),
+    ]
+}
+
+/// Collides a circle with an oriented box, with the normal pointing from the circle to the box.
+pub fn circle_obb(center: Vec2, radius: f32, obb: &Obb) -> Option<Contact> {
+    let local = obb.to_local(center);
+    let closest = local.clamp(-obb.half_extents, obb.half_extents);
+
+    // Work out which way the box would push the circle out, and how far.
+    let (push, depth) = if closest == local {
+        // The center is inside the box, so push it out through the nearest face.
+        let gap = obb.half_extents - local.abs();
+        if gap.x < gap.y {
+            (Vec2::new(local.x.signum(), 0.0), gap.x + radius)
+        } else {
+            (Vec2::new(0.0, local.y.signum()), gap.y + radius)
+        }
+    } else {
+        let offset = local - closest;
+        let distance = offset.length();
+        if distance > radius {
+            return None;
+        }
+        (offset / distance, radius - distance)
+    };
+
+    let (x_axis, y_axis) = obb.axes();
+    Some(Contact {
+        normal: -(x_axis * push.x + y_axis * push.y),
+        points: vec![Penetration {
+            point: obb.center + x_axis * closest.x + y_axis * closest.y,
+            depth,
+            id: 0,
+        }],
+    })
+}
+
+/// Collides a circle with a convex polygon, with the normal pointing from the circle to the polygon.
+pub fn circle_polygon(center: Vec2, radius: f32, vertices: &[Vec2]) -> Option<Contact> {
+    let (edge, separation) = face_separation(vertices, center);
+    if separation > radius {
+        return None;
+    }
+
+    let (push, depth, point) = if separation <= 0.0 {
+        // The center is inside, so push it out through the nearest face.
+        let normal = edge_normal(vertices[edge], vertices[(edge + 1) % vertices.len()]);
+        (normal, radius - separation, center - normal * separation)
+    } else {
+        // Outside, so the closest point could be anywhere along any edge.
+        let closest = (0..vertices.len())
+            .map(|i| closest_on_segment(vertices[i], vertices[(i + 1) % vertices.len()], center))
+            .min_by(|a, b| {
+                a.distance_squared(center)
+                    .partial_cmp(&b.distance_squared(center))
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            })?;
+        let offset = center - closest;
+        let distance = offset.length();
+        if distance > radius || distance <= f32::EPSILON {
+            return None;
+        }
+        (offset / distance, radius - distance, closest)
+    };
+
+    Some(Contact {
+        normal: -push,
+        points: vec![Penetration { point, depth, id: 0 }],
+    })
+}
+
+/// How far the point is in front of the polygon's nearest face. Negative values are inside.
+pub fn polygon_separation(vertices: &[Vec2], point: Vec2) -> f32 {
+    face_separation(vertices, point).1
+}
+
+/// The closest point to 'point' on the segment from 'start' to 'end'.
+pub fn closest_on_segment(start: Vec2, end: Vec2, point: Vec2) -> Vec2 {
+    let edge = end - start;
+    let length_squared = edge.length_squared();
+    if length_squared <= f32::EPSILON {
+        return start;
+    }
+    let t = ((point - start).dot(edge) / length_squared).clamp(0.0, 1.0);
+    start + edge * t
+}
+
+/// Collides two segments with rounded ends, thickened out by their radii. A circle is a segment that starts and ends at
+/// its center, and a bare line has no radius. The normal points from 'a' to 'b'.
+pub fn segment_segment(a: [Vec2; 2], a_radius: f32, b: [Vec2; 2], b_radius: f32) -> Option<Contact> {
+    let radius = a_radius + b_radius;
+
+    if let Some(crossing) = segment_intersection(a, b) {
+        // The cores cross, so find the cheapest way to lift one segment clear of the other's line.
+        let mut best: Option<(Vec2, f32)> = None;
+        for (reference, other, sign) in [(b, a, 1.0), (a, b, -1.0)] {
+            let normal = edge_normal(reference[0], reference[1]);
+            if normal == Vec2::ZERO {
+                continue;
+            }
+            let d0 = (other[0] - reference[0]).dot(normal);
+            let d1 = (other[1] - reference[0]).dot(normal);
+            // Either push 'a' back behind the line, or out in front of it.
+            let behind = d0.max(d1) + radius;
+            let in_front = -d0.min(d1) + radius;
+            let candidates = [(normal * sign, behind), (-normal * sign, in_front)];
+            for (n, depth) in candidates {
+                if best.map_or(true, |(_, best_depth)| depth < best_depth) {
+                    best = Some((n, depth));
+                }
+            }
+        }
+        let (normal, depth) = best?;
+        return Some(Contact {
+            normal,
+            points: vec![Penetration { point: crossing, depth, id: 0 }],
+        });
+    }
+
+    let (on_a, on_b) = closest_between_segments(a, b);
+    let offset = on_b - on_a;
+    let distance = offset.length();
+    if distance > radius {
+        return None;
+    }
+    let normal = if distance > f32::EPSILON {
+        offset / distance
+    } else {
+        // Touching exactly, so fall back to whichever segment has a direction.
+        let normal = edge_normal(b[0], b[1]);
+        if normal == Vec2::ZERO { edge_normal(a[1], a[0]) } else { -normal }
+    };
+    if normal == Vec2::ZERO {
+        return None;
+    }
+
+    Some(Contact {
+        normal,
+        points: vec![Penetration {
+            point: on_a + normal * a_radius,
+            depth: radius - distance,
+            id: 0,
+        }],
+    })
+}
+
+/// Collides a segment with rounded ends against a convex polygon, with the normal pointing from the segment to the polygon.
+pub fn segment_polygon(segment: [Vec2; 2], radius: f32, vertices: &[Vec2]) -> Option<Contact> {
+    let core: &[Vec2] = if segment[0] == segment[1] { &segment[..1] } else { &segment };
+
+    // If the bare segment already overlaps, the radius just makes it deeper.
+    if core.len() == 2 {
+        if let Some(mut contact) = polygon_polygon(&segment, vertices) {
+            for point in contact.points.iter_mut() {
+                point.depth += radius;
+            }
+            return Some(contact);
+        }
+    } else if polygon_separation(vertices, segment[0]) <= 0.0 {
+        return circle_polygon(segment[0], radius, vertices);
+    }
+
+    // Otherwise only the rounded edge can reach, so look for the nearest pair of points.
+    let (on_segment, on_polygon) = (0..vertices.len())
+        .map(|i| closest_between_segments(segment, [vertices[i], vertices[(i + 1) % vertices.len()]]))
+        .min_by(|(a0, b0), (a1, b1)| {
+            a0.distance_squared(*b0)
+                .partial_cmp(&a1.distance_squared(*b1))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        })?;
+    let offset = on_polygon - on_segment;
+    let distance = offset.length();
+    if distance > radius || distance <= f32::EPSILON {
+        return None;
+    }
+
+    Some(Contact {
+        normal: offset / distance,
+        points: vec![Penetration {
+            point: on_polygon,
+            depth: radius - distance,
+            id: 0,
+        }],
+    })
+}
+
+/// Where two segments cross, if they do.
+pub fn segment_intersection(a: [Vec2; 2], b: [Vec2; 2]) -> Option<Vec2> {
+    let r = a[1] - a[0];
+    let s = b[1] - b[0];
+    let denominator = r.perp_dot(s);
+    if denominator.abs() <= f32::EPSILON {
+        return None;
+    }
+    let offset = b[0] - a[0];
+    let t = offset.perp_dot(s) / denominator;
+    let u = offset.perp_dot(r) / denominator;
+    if (0.0..=1.0).contains(&t) && (0.0..=1.0).contains(&u) {
+        Some(a[0] + r * t)
+    } else {
+        None
+    }
+}
+
+/// The closest pair of points between two segments, one on each, assuming they do not cross.
+pub fn closest_between_segments(a: [Vec2; 2], b: [Vec2; 2]) -> (Vec2, Vec2) {
+    if let Some(crossing) = segment_intersection(a, b) {
+        return (crossing, crossing);
+    }
+    // Without a crossing, the closest pair always involves at least one end point.
+    [
+        (a[0], closest_on_segment(b[0], b[1], a[0])),
+        (a[1], closest_on_segment(b[0], b[1], a[1])),
+        (closest_on_segment(a[0], a[1], b[0]), b[0]),
+        (closest_on_segment(a[0], a[1], b[1]), b[1]),
+    ]
+    .into_iter()
+    .min_by(|(a0, b0), (a1, b1)| {
+        a0.distance_squared(*b0)
+            .partial_cmp(&a1.distance_squared(*b1))
+            .unwrap_or(std::cmp::Ordering::Equal)
+    })
+    .unwrap_or((a[0], b[0]))
+}
+
+/// Collides two convex polygons, both wound anti-clockwise. Finds the axis of least penetration, then clips the most
+/// opposed edge of one polygon against the edge of the other to get up to two contact points. A line can be passed in as
+/// a polygon with two vertices.
+pub fn polygon_polygon(a: &[Vec2], b: &[Vec2]) -> Option<Contact> {
+    let (edge_a, separation_a) = max_separation(a, b);
+    if separation_a > 0.0 {
+        return None;
+    }
+    let (edge_b, separation_b) = max_separation(b, a);
+    if separation_b > 0.0 {
+        return None;
+    }
+
+    // Prefer 'a' as the reference so the choice does not flicker between the two when they are about even.
+    const TOLERANCE: f32 = 0.005;
+    let flip = separation_b > separation_a + TOLERANCE;
+    let (reference, incident, edge) = if flip { (b, a, edge_b) } else { (a, b, edge_a) };
+
+    let v1 = reference[edge];
+    let v2 = reference[(edge + 1) % reference.len()];
+    let normal = edge_normal(v1, v2);
+
+    // The incident edge is the one facing most directly against the reference normal.
+    let incident_edge = (0..incident.len())
+        .min_by(|i, j| {
+            let ni = edge_normal(incident[*i], incident[(*i + 1) % incident.len()]).dot(normal);
+            let nj = edge_normal(incident[*j], incident[(*j + 1) % incident.len()]).dot(normal);
+            ni.partial_cmp(&nj).unwrap_or(std::cmp::Ordering::Equal)
+        })
+        .unwrap_or(0);
+    let segment = [
+        incident[incident_edge],
+        incident[(incident_edge + 1) % incident.len()],
+    ];
+
+    // Trim the incident edge down to the width of the reference edge.
+    let tangent = (v2 - v1).normalize_or_zero();
+    let segment = clip(segment, -tangent, -tangent.dot(v1))?;
+    let segment = clip(segment, tangent, tangent.dot(v2))?;
+
+    let mut points = Vec::with_capacity(2);
+    for (i, point) in segment.iter().enumerate() {
+        let separation = (*point - v1).dot(normal);
+        if separation <= 0.0 {
+            points.push(Penetration {
+                point: *point,
+                depth: -separation,
+                id: (flip as u32) << 24 | (edge as u32) << 16 | (incident_edge as u32) << 8 | i as u32,
+            });
+        }
+    }
+    if points.is_empty() {
+        return None;
+    }
+
+    // The reference normal points away from the reference polygon, so flip it if that was 'b'.
+    Some(Contact {
+        normal: if flip { -normal } else { normal },
+        points,
+    })
+}
+
+/// The outward normal of an edge on an anti-clockwise polygon.
+pub(crate) fn edge_normal(start: Vec2, end: Vec2) -> Vec2 {
+    let edge = end - start;
+    Vec2::new(edge.y, -edge.x).normalize_or_zero()
+}
+
+/// Finds the face of the polygon that the point is furthest in front of.
+fn face_separation(vertices: &[Vec2], point: Vec2) -> (usize, f32) {
+    max_separation(vertices, &[point])
+}
+
+/// Finds the edge of 'a' that 'b' is furthest in front of. A positive separation means there is a gap between them.
+fn max_separation(a: &[Vec2], b: &[Vec2]) -> (usize, f32) {
+    let mut best = (0, f32::MIN);
+    for i in 0..a.len() {
+        let start = a[i];
+        let normal = edge_normal(start, a[(i + 1) % a.len()]);
+        let separation = b
+            .iter()
+            .map(|v| (*v - start).dot(normal))
+            .fold(f32::MAX, f32::min);
+        if separation > best.1 {
+            best = (i, separation);
+        }
+    }
+    best
+}
+
+/// Clips a segment to the side of the plane where `normal.dot(x) <= offset`.
+fn clip(segment: [Vec2; 2], normal: Vec2, offset: f32) -> Option<[Vec2; 2]> {
+    let d0 = normal.dot(segment[0]) - offset;
+    let d1 = normal.dot(segment[1]) - offset;
+
+    let mut out = Vec::with_capacity(2);
+    if d0 <= 0.0 {
+        out.push(segment[0]);
+    }
+    if d1 <= 0.0 {
+        out.push(segment[1]);
+    }
+    if d0 * d1 < 0.0 {
+        let t = d0 / (d0 - d1);
+        out.push(segment[0] + (segment[1] - segment[0]) * t);
+    }
+
+    if out.len() == 2 {
+        Some([out[0], out[1]])
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(center: Vec2, half: f32) -> Vec<Vec2> {
+        vec![
+            center + Vec2::new(-half, -half),
+            center + Vec2::new(half, -half),
+            center + Vec2::new(half, half),
+            center + Vec2::new(-half, half),
+        ]
+    }
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-4
+    }
+
+    #[test]
+    fn resting_boxes_touch_at_two_clipped_points() {
+        let a = square(Vec2::ZERO, 1.0);
+        let b = square(Vec2::new(0.5, 1.9), 1.0);
+        let contact = polygon_polygon(&a, &b).unwrap();
+
+        assert!(close(contact.normal.x, 0.0) && close(contact.normal.y, 1.0));
+        assert_eq!(contact.points.len(), 2);
+        let mut xs: Vec<f32> = contact.points.iter().map(|p| p.point.x).collect();
+        xs.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert!(close(xs[0], -0.5) && close(xs[1], 1.0));
+        assert!(contact.points.iter().all(|p| close(p.depth, 0.1) && close(p.point.y, 0.9)));
+    }
+
+    #[test]
+    fn swapping_polygons_flips_the_normal() {
+        let a = square(Vec2::ZERO, 1.0);
+        let b = square(Vec2::new(0.5, 1.9), 1.0);
+        let contact = polygon_polygon(&b, &a).unwrap();
+
+        assert!(close(contact.normal.x, 0.0) && close(contact.normal.y, -1.0));
+        assert_eq!(contact.points.len(), 2);
+    }
+
+    #[test]
+    fn separated_polygons_do_not_touch() {
+        let a = square(Vec2::ZERO, 1.0);
+        let b = square(Vec2::new(0.0, 2.1), 1.0);
+        assert!(polygon_polygon(&a, &b).is_none());
+    }
+
+    #[test]
+    fn corner_into_face_gives_one_point() {
+        let a = square(Vec2::ZERO, 1.0);
+        // A diamond with its bottom corner pushed 0.2 into the top face of the square.
+        let b = vec![
+            Vec2::new(0.0, 0.8),
+            Vec2::new(1.0, 1.8),
+            Vec2::new(0.0, 2.8),
+            Vec2::new(-1.0, 1.8),
+        ];
+        let contact = polygon_polygon(&a, &b).unwrap();
+
+        assert!(close(contact.normal.y, 1.0));
+        assert_eq!(contact.points.len(), 1);
+        assert!(close(contact.points[0].depth, 0.2));
+        assert!(close(contact.points[0].point.x, 0.0) && close(contact.points[0].point.y, 0.8));
+    }
+
+    #[test]
+    fn parallel_segments_touch_along_their_rounding() {
+        let a = [Vec2::new(-1.0, 0.0), Vec2::new(1.0, 0.0)];
+        let b = [Vec2::new(-1.0, 0.8), Vec2::new(1.0, 0.8)];
+        let contact = segment_segment(a, 0.5, b, 0.5).unwrap();
+
+        assert!(close(contact.normal.x, 0.0) && close(contact.normal.y, 1.0));
+        assert_eq!(contact.points.len(), 1);
+        assert!(close(contact.points[0].depth, 0.2) && close(contact.points[0].point.y, 0.5));
+
+        let b = [Vec2::new(-1.0, 1.1), Vec2::new(1.0, 1.1)];
+        assert!(segment_segment(a, 0.5, b, 0.5).is_none());
+    }
+
+    #[test]
+    fn crossing_segments_take_the_shortest_way_out() {
+        // The vertical segment only pokes 0.2 below the horizontal one, so lifting it up is cheapest.
+        let a = [Vec2::new(-1.0, 0.0), Vec2::new(1.0, 0.0)];
+        let b = [Vec2::new(0.0, -0.2), Vec2::new(0.0, 1.0)];
+        let contact = segment_segment(a, 0.0, b, 0.0).unwrap();
+        assert!(close(contact.normal.x, 0.0) && close(contact.normal.y, 1.0));
+        assert!(close(contact.points[0].depth, 0.2));
+
+        let flipped = segment_segment(b, 0.0, a, 0.0).unwrap();
+        assert!(close(flipped.normal.y, -1.0) && close(flipped.points[0].depth, 0.2));
+    }
+
+    #[test]
+    fn circles_are_segments_that_go_nowhere() {
+        let a = [Vec2::new(-1.0, 0.0), Vec2::new(1.0, 0.0)];
+        let center = Vec2::new(1.6, 0.6);
+        let contact = segment_segment(a, 0.5, [center, center], 0.5).unwrap();
+
+        // Off the end of the segment, so it is pushed away from the rounded cap rather than straight up.
+        let expected = (center - a[1]).normalize();
+        assert!(close(contact.normal.x, expected.x) && close(contact.normal.y, expected.y));
+        assert!(close(contact.points[0].depth, 1.0 - (center - a[1]).length()));
+    }
+}
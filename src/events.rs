@@ -0,0 +1,26 @@
+use bevy::prelude::Entity;
+
+/// Sent on the first physics step two colliders touch. Steps run on a fixed timestep, so a frame can hold events from
+/// several steps or from none. The order of the entities carries no meaning.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CollisionStarted(pub Entity, pub Entity);
+
+/// Sent on the first physics step two colliders stop touching, or when one of them loses its
+/// [crate::components::Collider] or [crate::components::RigidBody]. The order of the entities carries no meaning.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CollisionEnded(pub Entity, pub Entity);
+
+/// Sent on the first physics step something overlaps a [crate::components::Sensor]. The sensor is always the first
+/// entity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TriggerEntered(pub Entity, pub Entity);
+
+/// Sent on every physics step after the first that something keeps overlapping a [crate::components::Sensor], so a
+/// frame can see it several times or not at all. The sensor is always the first entity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TriggerStay(pub Entity, pub Entity);
+
+/// Sent on the first physics step something stops overlapping a [crate::components::Sensor]. The sensor is always the
+/// first entity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TriggerExited(pub Entity, pub Entity);
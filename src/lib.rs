@@ -1,6 +1,11 @@
+pub mod broad_phase;
 pub mod bundles;
+pub mod cast;
 pub mod components;
+pub mod contact;
+pub mod events;
 pub mod pipeline;
+pub mod query;
 pub mod resources;
 pub mod systems;
 
@@ -9,5 +14,5 @@ mod plugin;
 mod shapes;
 
 pub use mesh::*;
-pub use plugin::FishicsPlugin;
+pub use plugin::{FishicsPlugin, PHYSICS_STAGE};
 pub use shapes::*;
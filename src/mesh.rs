@@ -1,4 +1,5 @@
 use bevy::{
+    math::Vec2,
     render::mesh::{Indices, Mesh, PrimitiveTopology}, 
 };
 
@@ -48,11 +49,56 @@ pub fn build_circle(radius: f32, vertices: usize) -> Mesh {
     mesh
 }
 
-// pub fn build_line(start: Vec2, end: Vec2) -> Mesh {
-//     let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+/// Builds a convex polygon as a fan of triangles from its first vertex.
+pub fn build_polygon(vertices: &[Vec2]) -> Mesh {
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+
+    let n_vertices = vertices.len();
+    let n_triangles = n_vertices.saturating_sub(2) as u32;
 
-//     let mut positions: Vec<[f32; 3]> = Vec::with_capacity(3);
-//     let mut normals: Vec<[f32; 3]> = Vec::with_capacity(3);
-//     let mut uvs: Vec<[f32; 2]> = Vec::with_capacity(3);
-//     let mut indices: Vec<u32> = Vec::with_capacity(3);
-// }
\ No newline at end of file
+    let min = vertices.iter().fold(Vec2::splat(f32::MAX), |min, v| min.min(*v));
+    let max = vertices.iter().fold(Vec2::splat(f32::MIN), |max, v| max.max(*v));
+    let size = (max - min).max(Vec2::splat(f32::EPSILON));
+
+    let mut positions: Vec<[f32; 3]> = Vec::with_capacity(n_vertices);
+    let mut normals: Vec<[f32; 3]> = Vec::with_capacity(n_vertices);
+    let mut uvs: Vec<[f32; 2]> = Vec::with_capacity(n_vertices);
+    let mut indices: Vec<u32> = Vec::with_capacity((n_triangles as usize) * 3);
+
+    for vertex in vertices {
+        let uv = (*vertex - min) / size;
+        positions.push([vertex.x, vertex.y, 0.]);
+        normals.push([0., 0., 1.]);
+        uvs.push([uv.x, 1.0 - uv.y]);
+    }
+
+    for i in 0..n_triangles {
+        indices.push(0);
+        indices.push(i + 1);
+        indices.push(i + 2);
+    }
+
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh.set_indices(Some(Indices::U32(indices)));
+
+    mesh
+}
+
+/// Builds a line with rounded ends, thickened out by the radius.
+pub fn build_line(start: Vec2, end: Vec2, radius: f32, cap_vertices: usize) -> Mesh {
+    let direction = (end - start).normalize_or_zero();
+    let direction = if direction == Vec2::ZERO { Vec2::X } else { direction };
+    let base = direction.y.atan2(direction.x);
+
+    // Half a circle around each end, which together make a convex outline.
+    let mut outline = Vec::with_capacity((cap_vertices + 1) * 2);
+    for (center, offset) in [(end, -PI2 / 4.0), (start, PI2 / 4.0)] {
+        for i in 0..=cap_vertices {
+            let angle = base + offset + (i as f32) / (cap_vertices.max(1) as f32) * PI2 / 2.0;
+            outline.push(center + Vec2::new(angle.cos(), angle.sin()) * radius);
+        }
+    }
+    build_polygon(&outline)
+}
@@ -1,21 +1,61 @@
-use crate::components::{Mass, RigidBody, Velocity, PhysicsMaterial};
-use bevy::prelude::*;
+use crate::{components::{BodyType, CenterOfMass, CombineRule, Inertia, Mass, PartId, RigidBody, Velocity, PhysicsMaterial}, contact::Contact, shapes::turn};
+use bevy::{prelude::*, utils::{HashMap, HashSet}};
 use prima::prelude::*;
 #[derive(Debug, Clone)]
 pub struct BroadPhasePairs {
     pub pairs: Vec<(Entity, Entity)>,
 }
 
+/// How closely a manifold's normal has to line up with last step's for its impulses to be carried over.
+pub const PERSISTENCE_THRESHOLD: f32 = 0.95;
+
 #[derive(Debug, Clone)]
 pub struct Manifolds(Vec<Manifold>);
+
+/// Entities that have lost their [RigidBody] or [crate::components::Collider] since the broad phase last ran.
+#[derive(Debug, Clone, Default)]
+pub struct PendingRemovals(pub Vec<Entity>);
+
+/// Every pair of entities that were touching at the end of the last physics step.
+#[derive(Debug, Clone, Default)]
+pub struct ContactPairs(HashSet<(Entity, Entity)>);
+
+/// Overlaps involving a sensor found by the narrow phase this step, with the sensor first.
+#[derive(Debug, Clone, Default)]
+pub struct SensorOverlaps {
+    pub pairs: Vec<(Entity, Entity)>,
+}
+
+/// Every sensor overlap at the end of the last physics step, with the sensor first.
+#[derive(Debug, Clone, Default)]
+pub struct TriggerPairs(pub ContactPairs);
 #[derive(Debug, Clone)]
 pub struct Manifold {
     pub a: Entity,
     pub b: Entity,
     pub n: Vector<f32>,
-    pub p: f32,
     pub f: Option<f32>,
+    /// Up to two points where the shapes touch, all sharing the normal.
+    pub points: Vec<ContactPoint>,
+    /// Which part of a compound body 'a' was hit on, if it has parts.
+    pub part_a: Option<PartId>,
+    /// Which part of a compound body 'b' was hit on, if it has parts.
+    pub part_b: Option<PartId>,
+}
+
+/// A single point of a [Manifold].
+#[derive(Debug, Clone)]
+pub struct ContactPoint {
     pub c: Point<f32>,
+    pub p: f32,
+    /// Identifies which features of the two shapes the point came from, so it can be matched up next step.
+    pub id: u32,
+    /// Accumulated normal impulse, for resolvers that iterate.
+    pub jn: f32,
+    /// Accumulated tangent impulse, for resolvers that iterate.
+    pub jt: f32,
+    /// Separating velocity the resolver is aiming for, worked out once per step.
+    pub bias: f32,
 }
 
 // Impulse pipeline helper-structs.
@@ -37,6 +77,14 @@ pub struct ImpulseObject {
     pub mi: f32,
     /// Coefficient of restitution
     pub cr: f32,
+    /// Restitution combine rule requested by the material, if any
+    pub rc: Option<CombineRule>,
+    /// Coefficient of static friction
+    pub sf: f32,
+    /// Coefficient of dynamic friction
+    pub df: f32,
+    /// Friction combine rule requested by the material, if any
+    pub fc: Option<CombineRule>,
 }
 
 /// Result of a collision on an object.
@@ -50,34 +98,42 @@ pub struct ImpulseResult {
     pub t: Vector<f32>,
 }
 
+/// Gathers everything the resolver needs to know about the two bodies in a manifold. Returns None if either body has
+/// gone. Only dynamic bodies are given a mass, so everything else acts as an immovable object.
 pub fn generate_impulse_pair(
     v: &mut Query<&mut Velocity>,
     rb: &mut Query<&mut RigidBody>,
     manifold: &Manifold,
     m: &Query<&Mass>,
+    inertia: &Query<&Inertia>,
+    centers: &Query<&CenterOfMass>,
+    types: &Query<&BodyType>,
     mats: &Res<Assets<PhysicsMaterial>>,
     mat_handles: &Query<&Handle<PhysicsMaterial>>,
-) -> (ImpulseObject, ImpulseObject) {
+) -> Option<(ImpulseObject, ImpulseObject)> {
+    let dynamic_1 = types.get(manifold.a).copied().unwrap_or_default().is_dynamic();
+    let dynamic_2 = types.get(manifold.b).copied().unwrap_or_default().is_dynamic();
     let vel_1 = v.get(manifold.a).ok();
     let vel_2 = v.get(manifold.b).ok();
-    let mass_1 = m.get(manifold.a).ok();
-    let mass_2 = m.get(manifold.b).ok();
+    let mass_1 = m.get(manifold.a).ok().filter(|_| dynamic_1);
+    let mass_2 = m.get(manifold.b).ok().filter(|_| dynamic_2);
 
-    let mi_1 = 1.0;
-    let mi_2 = 1.0;
-    let mut cr_1 = 1.0;
-    let mut cr_2 = 1.0;
-
-    if let Some(handle) = mat_handles.get(manifold.a).ok() {
-        if let Some(mat) = mats.get(handle) {
-            cr_1 = mat.restitution;
-        }
-    }
-    if let Some(handle) = mat_handles.get(manifold.b).ok() {
-        if let Some(mat) = mats.get(handle) {
-            cr_2 = mat.restitution;
-        }
-    }
+    // Anything without an inertia can't be spun.
+    let mi_1 = inertia.get(manifold.a).ok().filter(|_| dynamic_1).map(|i| i.inv()).unwrap_or(0.0);
+    let mi_2 = inertia.get(manifold.b).ok().filter(|_| dynamic_2).map(|i| i.inv()).unwrap_or(0.0);
+    // Bodies without a material are perfectly bouncy and frictionless.
+    let mat_1 = mat_handles.get(manifold.a).ok().and_then(|handle| mats.get(handle));
+    let mat_2 = mat_handles.get(manifold.b).ok().and_then(|handle| mats.get(handle));
+    let cr_1 = mat_1.map(|mat| mat.restitution).unwrap_or(1.0);
+    let cr_2 = mat_2.map(|mat| mat.restitution).unwrap_or(1.0);
+    let sf_1 = mat_1.map(|mat| mat.static_friction).unwrap_or(0.0);
+    let sf_2 = mat_2.map(|mat| mat.static_friction).unwrap_or(0.0);
+    let df_1 = mat_1.map(|mat| mat.dynamic_friction).unwrap_or(0.0);
+    let df_2 = mat_2.map(|mat| mat.dynamic_friction).unwrap_or(0.0);
+    let rc_1 = mat_1.and_then(|mat| mat.restitution_combine);
+    let rc_2 = mat_2.and_then(|mat| mat.restitution_combine);
+    let fc_1 = mat_1.and_then(|mat| mat.friction_combine);
+    let fc_2 = mat_2.and_then(|mat| mat.friction_combine);
 
 
     let v1 = if let Some(vel_1) = vel_1 {
@@ -120,8 +176,14 @@ pub fn generate_impulse_pair(
     } else {
         0.0
     };
-    let com1 = rb.get(manifold.a).unwrap().position;
-    let com2 = rb.get(manifold.b).unwrap().position;
+    // Lever arms are measured from the center of mass, which compound bodies can have away from their position.
+    let center = |entity: Entity| {
+        let body = rb.get(entity).ok()?;
+        let offset = centers.get(entity).map(|c| c.raw()).unwrap_or(Vec2::ZERO);
+        Some(body.position + turn(offset, body.applied_rotation()))
+    };
+    let com1 = center(manifold.a)?;
+    let com2 = center(manifold.b)?;
 
     let a: ImpulseObject = ImpulseObject {
         m: m1,
@@ -130,6 +192,10 @@ pub fn generate_impulse_pair(
         r: a1,
         c: Point::new(com1.x, com1.y),
         cr: cr_1,
+        rc: rc_1,
+        sf: sf_1,
+        df: df_1,
+        fc: fc_1,
         mi: mi_1,
     };
 
@@ -140,10 +206,14 @@ pub fn generate_impulse_pair(
         r: a2,
         c: Point::new(com2.x, com2.y),
         cr: cr_2,
+        rc: rc_2,
+        sf: sf_2,
+        df: df_2,
+        fc: fc_2,
         mi: mi_2,
     };
 
-    (a, b)
+    Some((a, b))
 }
 
 impl ImpulseResult {
@@ -184,19 +254,36 @@ impl BroadPhasePairs {
 }
 
 impl Manifold {
-    pub fn new(a: Entity, b: Entity, collision: Collision<f32>) -> Self {
-        let n = collision.normal.normalize();
-        let p = collision.depth;
+    pub fn new(a: Entity, b: Entity, contact: Contact) -> Self {
+        let points = contact
+            .points
+            .iter()
+            .map(|point| ContactPoint {
+                c: Point::new(point.point.x, point.point.y),
+                p: point.depth,
+                id: point.id,
+                jn: 0.0,
+                jt: 0.0,
+                bias: 0.0,
+            })
+            .collect();
         Self {
             a,
             b,
-            n,
-            p,
+            n: Vector::new(contact.normal.x, contact.normal.y),
             f: None,
-            c: collision.point,
+            points,
+            part_a: None,
+            part_b: None,
         }
     }
 
+    pub fn with_parts(mut self, part_a: Option<PartId>, part_b: Option<PartId>) -> Self {
+        self.part_a = part_a;
+        self.part_b = part_b;
+        self
+    }
+
     pub fn with_initial_force(&self, f: f32) -> Self {
         let mut new = self.clone();
         new.f = Some(f);
@@ -206,6 +293,20 @@ impl Manifold {
     pub fn f(&self) -> f32 {
         self.f.unwrap_or(0.0)
     }
+
+    /// The penetration depth of the deepest point.
+    pub fn depth(&self) -> f32 {
+        self.points.iter().fold(0.0, |depth, point| depth.max(point.p))
+    }
+
+    /// Identifies the manifold no matter which way round the entities were found.
+    pub fn key(&self) -> (Entity, Entity, Option<PartId>, Option<PartId>) {
+        if self.a <= self.b {
+            (self.a, self.b, self.part_a, self.part_b)
+        } else {
+            (self.b, self.a, self.part_b, self.part_a)
+        }
+    }
 }
 
 impl Manifolds {
@@ -217,7 +318,111 @@ impl Manifolds {
         self.0 = manifolds;
     }
 
+    /// Replaces the stored manifolds, carrying the accumulated impulses over to any point that was also found last step.
+    /// Points that were not found again are dropped.
+    pub fn update(&mut self, mut manifolds: Vec<Manifold>) {
+        let old: HashMap<_, Manifold> = self.0.drain(..).map(|m| (m.key(), m)).collect();
+        for manifold in manifolds.iter_mut() {
+            if let Some(previous) = old.get(&manifold.key()) {
+                // The tangent flips along with the normal if the pair was found the other way round.
+                let flipped = previous.a != manifold.a;
+                let n = if flipped { -previous.n } else { previous.n };
+
+                // A manifold that has swung round too far is a different contact.
+                if n.dot(&manifold.n) < PERSISTENCE_THRESHOLD {
+                    continue;
+                }
+                for point in manifold.points.iter_mut() {
+                    if let Some(old_point) = previous.points.iter().find(|p| p.id == point.id) {
+                        point.jn = old_point.jn;
+                        point.jt = if flipped { -old_point.jt } else { old_point.jt };
+                    }
+                }
+            }
+        }
+        self.0 = manifolds;
+    }
+
     pub fn iter(&self) -> impl Iterator<Item = &Manifold> {
         self.0.iter()
     }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Manifold> {
+        self.0.iter_mut()
+    }
+}
+
+impl ContactPairs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Orders the pair so it can be looked up no matter which way round it was found.
+    pub fn key(a: Entity, b: Entity) -> (Entity, Entity) {
+        if a <= b {
+            (a, b)
+        } else {
+            (b, a)
+        }
+    }
+
+    pub fn contains(&self, a: Entity, b: Entity) -> bool {
+        self.0.contains(&Self::key(a, b))
+    }
+
+    /// Replaces the stored pairs, returning the ones that are new and the ones that have gone.
+    pub fn update(&mut self, current: HashSet<(Entity, Entity)>) -> (Vec<(Entity, Entity)>, Vec<(Entity, Entity)>) {
+        let started = current.difference(&self.0).copied().collect();
+        let ended = self.0.difference(&current).copied().collect();
+        self.0 = current;
+        (started, ended)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &(Entity, Entity)> {
+        self.0.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contact(a: Entity, b: Entity, n: Vector<f32>) -> Manifold {
+        Manifold {
+            a,
+            b,
+            n,
+            f: None,
+            points: vec![ContactPoint {
+                c: Point::new(0.0, 0.0),
+                p: 0.1,
+                id: 0,
+                jn: 0.0,
+                jt: 0.0,
+                bias: 0.0,
+            }],
+            part_a: None,
+            part_b: None,
+        }
+    }
+
+    #[test]
+    fn impulses_carry_over_to_the_same_contact() {
+        let (a, b) = (Entity::from_raw(0), Entity::from_raw(1));
+        let mut manifolds = Manifolds::new();
+        let mut first = contact(a, b, Vector::new(1.0, 0.0));
+        first.points[0].jn = 2.0;
+        first.points[0].jt = 0.5;
+        manifolds.set(vec![first]);
+
+        // Found the other way round, so the normal and the tangent both point the other way.
+        manifolds.update(vec![contact(b, a, Vector::new(-1.0, 0.0))]);
+        let m = manifolds.iter().next().unwrap();
+        assert_eq!((m.points[0].jn, m.points[0].jt), (2.0, -0.5));
+
+        // Swung round by a right angle, so it starts from nothing.
+        manifolds.update(vec![contact(b, a, Vector::new(0.0, 1.0))]);
+        let m = manifolds.iter().next().unwrap();
+        assert_eq!((m.points[0].jn, m.points[0].jt), (0.0, 0.0));
+    }
 }
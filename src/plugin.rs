@@ -1,14 +1,25 @@
+use std::marker::PhantomData;
+
+use crate::broad_phase::*;
 use crate::components::*;
+use crate::events::*;
 use crate::pipeline::*;
 use crate::resources::*;
-use crate::systems::{core::*, render::*, transforms::*};
-use bevy::prelude::*;
+use crate::systems::{core::*, render::*, time::*, transforms::*};
+use bevy::{prelude::*, transform::TransformSystem};
 use bevy_inspector_egui::RegisterInspectable;
 
-pub struct FishicsPlugin {
+/// The stage the physics pipeline runs in. It runs after [CoreStage::Update], as many times a frame as the fixed timestep asks for.
+pub const PHYSICS_STAGE: &str = "fishics_step";
+
+/// The physics plugin. `B` picks the [BroadPhase] used to find potential collisions, defaulting to [SweepAndPrune], and `R`
+/// picks the [ImpulseResolver] used to push them apart, defaulting to [ClassicImpulseResolver].
+pub struct FishicsPlugin<B: BroadPhase = SweepAndPrune, R: ImpulseResolver = ClassicImpulseResolver> {
     pub apply_transforms: bool,
     pub render_colliders: bool,
     pub config: FishicsConfig,
+    pub broad_phase: PhantomData<B>,
+    pub resolver: PhantomData<R>,
 }
 
 impl Default for FishicsPlugin {
@@ -17,46 +28,114 @@ impl Default for FishicsPlugin {
             apply_transforms: true,
             render_colliders: true,
             config: FishicsConfig::default(),
+            broad_phase: PhantomData,
+            resolver: PhantomData,
         }
     }
 }
 
-impl Plugin for FishicsPlugin
+impl<B: BroadPhase, R: ImpulseResolver> FishicsPlugin<B, R> {
+    /// Swaps the broad phase used by the plugin.
+    pub fn with_broad_phase<T: BroadPhase>(self) -> FishicsPlugin<T, R> {
+        FishicsPlugin {
+            apply_transforms: self.apply_transforms,
+            render_colliders: self.render_colliders,
+            config: self.config,
+            broad_phase: PhantomData,
+            resolver: PhantomData,
+        }
+    }
+
+    /// Swaps the impulse resolver used by the plugin.
+    pub fn with_resolver<T: ImpulseResolver>(self) -> FishicsPlugin<B, T> {
+        FishicsPlugin {
+            apply_transforms: self.apply_transforms,
+            render_colliders: self.render_colliders,
+            config: self.config,
+            broad_phase: PhantomData,
+            resolver: PhantomData,
+        }
+    }
+}
+
+impl<B: BroadPhase, R: ImpulseResolver> Plugin for FishicsPlugin<B, R>
 {
     fn build(&self, app: &mut App) {
         app.register_inspectable::<RigidBody>()
+            .register_inspectable::<BodyType>()
             .register_inspectable::<Collider>()
+            .register_inspectable::<CompoundCollider>()
+            .register_inspectable::<ColliderOffset>()
+            .register_inspectable::<CollisionGroups>()
             .register_inspectable::<Forces>()
             .register_inspectable::<Velocity>()
             .register_inspectable::<Mass>()
+            .register_inspectable::<GravityScale>()
             .register_inspectable::<Inertia>()
+            .register_inspectable::<CenterOfMass>()
+            .register_inspectable::<Sleep>()
             .register_inspectable::<PhysicsMaterial>()
             .register_inspectable::<ColliderRender>()
-            .register_inspectable::<ClassicImpulseResolver>();
+            .register_inspectable::<ClassicImpulseResolver>()
+            .register_inspectable::<SequentialImpulseResolver>();
 
         app.insert_resource(BroadPhasePairs::new())
             .insert_resource(Manifolds::new())
-            .insert_resource(self.config.clone())
-            .insert_resource(ClassicImpulseResolver::default());
+            .insert_resource(ContactPairs::new())
+            .insert_resource(SensorOverlaps::default())
+            .insert_resource(TriggerPairs::default())
+            .insert_resource(PendingRemovals::default())
+            .insert_resource(PhysicsTime::new())
+            .insert_resource(self.config.clone());
+
+        // Only insert the broad phase and resolver if the user has not already supplied configured ones.
+        app.init_resource::<B>()
+            .init_resource::<R>();
 
         app.add_asset::<PhysicsMaterial>();
 
-        app.add_system(integration.before(narrow_phase))
-            .add_system(narrow_phase.before(impulse_resolution::<ClassicImpulseResolver>))
-            .add_system(broad_phase.before(narrow_phase).after(integration));
+        app.add_event::<CollisionStarted>()
+            .add_event::<CollisionEnded>()
+            .add_event::<TriggerEntered>()
+            .add_event::<TriggerStay>()
+            .add_event::<TriggerExited>();
+
+        app.add_stage_after(
+            CoreStage::Update,
+            PHYSICS_STAGE,
+            SystemStage::parallel().with_run_criteria(fixed_timestep),
+        );
+
+        // The stage runs once per substep. Interpolation and events only care about whole steps.
+        app.add_system_to_stage(
+            PHYSICS_STAGE,
+            store_previous_state.with_run_criteria(first_substep).before(integration),
+        )
+            .add_system_to_stage(PHYSICS_STAGE, mass_properties.before(integration))
+            .add_system_to_stage(PHYSICS_STAGE, integration.before(narrow_phase))
+            .add_system_to_stage(PHYSICS_STAGE, narrow_phase.before(impulse_resolution::<R>))
+            .add_system_to_stage(PHYSICS_STAGE, continuous_collision::<B>.after(integration))
+            .add_system_to_stage(PHYSICS_STAGE, broad_phase::<B>.before(narrow_phase).after(continuous_collision::<B>))
+            .add_system_to_stage(PHYSICS_STAGE, collision_events.with_run_criteria(last_substep).after(narrow_phase))
+            .add_system_to_stage(PHYSICS_STAGE, trigger_events.with_run_criteria(last_substep).after(narrow_phase))
+            .add_system_to_stage(PHYSICS_STAGE, impulse_resolution::<R>)
+            .add_system_to_stage(PHYSICS_STAGE, sleeping.after(impulse_resolution::<R>))
+            .add_system_to_stage(PHYSICS_STAGE, wake_removed_contacts.before(integration).before(broad_phase::<B>))
+            .add_system_to_stage(CoreStage::Last, collect_removals);
 
         if self.config.max_speed() > 0.0 {
-            app.add_system(speed_limmit.before(integration));
+            app.add_system_to_stage(PHYSICS_STAGE, speed_limmit.before(integration));
         }
 
         if self.apply_transforms {
-            app.add_system(apply_transforms.after(integration));
+            app.add_system_to_stage(
+                CoreStage::PostUpdate,
+                apply_transforms.before(TransformSystem::TransformPropagate),
+            );
         }
 
         if self.render_colliders {
             app.add_system_to_stage(CoreStage::PreUpdate, create_mesh_renders.after(integration));
         }
-
-        app.add_system(impulse_resolution::<ClassicImpulseResolver>);
     }
 }
@@ -0,0 +1,297 @@
+use bevy::{ecs::system::SystemParam, prelude::*};
+use prima::prelude::*;
+
+use crate::{
+    broad_phase::{BroadPhase, SweepAndPrune},
+    cast,
+    components::{CollisionGroups, PartId, RigidBody, Sensor, ALL_GROUPS},
+    systems::core::{BodyColliders, BodyPart},
+    AbstractShape, ShapeWrapper,
+};
+
+/// Decides which colliders a query can hit.
+#[derive(Debug, Clone)]
+pub struct QueryFilter {
+    /// Compared against each collider's groups, the same way two colliders are.
+    pub groups: CollisionGroups,
+    /// Bodies the query passes straight through.
+    pub exclude: Vec<Entity>,
+    /// Whether sensors can be hit.
+    pub sensors: bool,
+}
+
+/// Where a ray hit a collider. Points and distances are in the same units as [RigidBody::position].
+#[derive(Debug, Clone, Copy)]
+pub struct RayHit {
+    pub entity: Entity,
+    /// The part of a compound body that was hit, or None for the body's own [crate::components::Collider].
+    pub part: Option<PartId>,
+    pub point: Vec2,
+    /// Points out of the surface that was hit.
+    pub normal: Vec2,
+    pub distance: f32,
+}
+
+/// Where a cast shape first touched a collider.
+#[derive(Debug, Clone, Copy)]
+pub struct ShapeHit {
+    pub entity: Entity,
+    pub part: Option<PartId>,
+    pub point: Vec2,
+    /// Points from the cast shape into the collider it hit.
+    pub normal: Vec2,
+    /// How much of the translation was covered before touching, from 0.0 to 1.0.
+    pub toi: f32,
+}
+
+/// Lets systems ask questions about the colliders in the world, such as what a ray would hit. Candidates come from the
+/// broad phase, so they are found where they were at the end of the last physics step. Using a broad phase other than
+/// the default means naming it here too, the same as on the plugin.
+#[derive(SystemParam)]
+pub struct PhysicsQuery<'w, 's, B: BroadPhase = SweepAndPrune> {
+    broad: Res<'w, B>,
+    bodies: Query<'w, 's, &'static RigidBody>,
+    colliders: BodyColliders<'w, 's>,
+    sensors: Query<'w, 's, (), With<Sensor>>,
+}
+
+impl QueryFilter {
+    pub fn new(groups: CollisionGroups) -> Self {
+        Self {
+            groups,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_groups(mut self, groups: CollisionGroups) -> Self {
+        self.groups = groups;
+        self
+    }
+
+    pub fn exclude(mut self, entity: Entity) -> Self {
+        self.exclude.push(entity);
+        self
+    }
+
+    pub fn with_sensors(mut self, sensors: bool) -> Self {
+        self.sensors = sensors;
+        self
+    }
+}
+
+impl Default for QueryFilter {
+    fn default() -> Self {
+        Self {
+            groups: CollisionGroups::new(ALL_GROUPS, ALL_GROUPS),
+            exclude: Vec::new(),
+            sensors: false,
+        }
+    }
+}
+
+impl<'w, 's, B: BroadPhase> PhysicsQuery<'w, 's, B> {
+    /// The first collider along a ray, if any is within `max_toi` of the origin.
+    pub fn cast_ray(&self, origin: Vec2, direction: Vec2, max_toi: f32, filter: &QueryFilter) -> Option<RayHit> {
+        self.cast_ray_all(origin, direction, max_toi, filter).into_iter().next()
+    }
+
+    /// Every collider along a ray within `max_toi` of the origin, nearest first. Compound bodies can be hit once for
+    /// each of their parts.
+    pub fn cast_ray_all(&self, origin: Vec2, direction: Vec2, max_toi: f32, filter: &QueryFilter) -> Vec<RayHit> {
+        let direction = direction.normalize_or_zero();
+        if direction == Vec2::ZERO || max_toi < 0.0 {
+            return Vec::new();
+        }
+        let end = origin + direction * max_toi;
+        let region = Aabr::new(
+            Point::new(origin.x.min(end.x), origin.y.min(end.y)),
+            Point::new(origin.x.max(end.x), origin.y.max(end.y)),
+        );
+
+        let mut hits = Vec::new();
+        for (entity, rb, part) in self.candidates(&region, filter) {
+            let shape = part.wrap(rb);
+            if let Some((distance, normal)) = shape.cast_ray(origin, direction, max_toi) {
+                hits.push(RayHit {
+                    entity,
+                    part: part.id,
+                    point: origin + direction * distance,
+                    normal,
+                    distance,
+                });
+            }
+        }
+        hits.sort_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap_or(std::cmp::Ordering::Equal));
+        hits
+    }
+
+    /// Sweeps a shape from `position` along `translation`, returning the first collider it would touch. Rotation is in
+    /// radians and stays fixed for the whole sweep.
+    pub fn cast_shape(
+        &self,
+        shape: &AbstractShape,
+        position: Vec2,
+        rotation: f32,
+        translation: Vec2,
+        filter: &QueryFilter,
+    ) -> Option<ShapeHit> {
+        let start = shape.wrap(Point::new(position.x, position.y), rotation).bounding_rect();
+        let region = cast::swept_bounds(&start, translation);
+
+        let mut first: Option<ShapeHit> = None;
+        for (entity, rb, part) in self.candidates(&region, filter) {
+            let target = part.wrap(rb);
+            if let Some((toi, contact)) = cast::cast_shape(shape, position, rotation, translation, &target) {
+                if first.map_or(true, |hit| toi < hit.toi) {
+                    first = Some(ShapeHit {
+                        entity,
+                        part: part.id,
+                        point: contact.deepest().map(|p| p.point).unwrap_or(position + translation * toi),
+                        normal: contact.normal,
+                        toi,
+                    });
+                }
+            }
+        }
+        first
+    }
+
+    /// Every body with a collider containing the point.
+    pub fn colliders_at_point(&self, point: Vec2, filter: &QueryFilter) -> Vec<Entity> {
+        let region = Aabr::new(Point::new(point.x, point.y), Point::new(point.x, point.y));
+        self.matching(&region, filter, |shape| shape.contains_point(point))
+    }
+
+    /// Every body with a collider overlapping the shape, placed at a position with a rotation in radians.
+    pub fn colliders_overlapping(
+        &self,
+        shape: &AbstractShape,
+        position: Vec2,
+        rotation: f32,
+        filter: &QueryFilter,
+    ) -> Vec<Entity> {
+        let shape = shape.wrap(Point::new(position.x, position.y), rotation);
+        self.matching(&shape.bounding_rect(), filter, |other| shape.contact(other).is_some())
+    }
+
+    /// Every body with a collider touching the rectangle.
+    pub fn colliders_in_region(&self, region: &Aabr<f32>, filter: &QueryFilter) -> Vec<Entity> {
+        let shape = ShapeWrapper::Aabr(*region);
+        self.matching(region, filter, |other| shape.contact(other).is_some() || shape.enveloping(other))
+    }
+
+    /// Bodies with at least one part in the region that passes the test, each given once.
+    fn matching(&self, region: &Aabr<f32>, filter: &QueryFilter, test: impl Fn(&ShapeWrapper) -> bool) -> Vec<Entity> {
+        let mut entities = Vec::new();
+        for (entity, rb, part) in self.candidates(region, filter) {
+            if entities.last() != Some(&entity) && test(&part.wrap(rb)) {
+                entities.push(entity);
+            }
+        }
+        entities
+    }
+
+    /// Every part of every body in the region that the filter lets through.
+    fn candidates<'a>(
+        &'a self,
+        region: &Aabr<f32>,
+        filter: &'a QueryFilter,
+    ) -> impl Iterator<Item = (Entity, &'a RigidBody, BodyPart)> + 'a {
+        self.broad
+            .query(region, filter.groups)
+            .into_iter()
+            .filter(move |entity| !filter.exclude.contains(entity))
+            .filter(move |entity| filter.sensors || self.sensors.get(*entity).is_err())
+            .filter_map(move |entity| self.bodies.get(entity).ok().map(|rb| (entity, rb)))
+            .flat_map(move |(entity, rb)| {
+                self.colliders
+                    .parts(entity)
+                    .into_iter()
+                    .filter(|part| filter.groups.interacts_with(&part.groups))
+                    .map(move |part| (entity, rb, part))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::SystemState;
+
+    use super::*;
+    use crate::components::Collider;
+
+    fn spawn(world: &mut World, x: f32, collider: Collider) -> Entity {
+        let rb = RigidBody::new(Point::new(x, 0.0));
+        let (bounds, groups) = (collider.global_aabr(&rb), collider.groups);
+        let entity = world.spawn().insert_bundle((rb, collider)).id();
+        world.resource_mut::<SweepAndPrune>().insert(entity, bounds, groups);
+        entity
+    }
+
+    /// A small sensor, a circle and a square, spread along the x axis.
+    fn scene() -> (World, [Entity; 3]) {
+        let mut world = World::new();
+        world.insert_resource(SweepAndPrune::new());
+        let sensor = spawn(&mut world, 1.5, Collider::circle(0.25));
+        world.entity_mut(sensor).insert(Sensor);
+        let near = spawn(&mut world, 3.0, Collider::circle(1.0));
+        let far = spawn(&mut world, 6.0, Collider::square(2.0));
+        (world, [sensor, near, far])
+    }
+
+    fn sorted(mut entities: Vec<Entity>) -> Vec<Entity> {
+        entities.sort();
+        entities
+    }
+
+    #[test]
+    fn rays_and_shapes_hit_the_nearest_collider() {
+        let (mut world, [sensor, near, far]) = scene();
+        let mut state: SystemState<PhysicsQuery> = SystemState::new(&mut world);
+        let query = state.get_mut(&mut world);
+        let filter = QueryFilter::default();
+
+        let hit = query.cast_ray(Vec2::ZERO, Vec2::X, 10.0, &filter).unwrap();
+        assert_eq!((hit.entity, hit.distance, hit.normal), (near, 2.0, -Vec2::X));
+        assert_eq!(hit.point, Vec2::new(2.0, 0.0));
+        let hit = query.cast_ray(Vec2::ZERO, Vec2::X, 10.0, &filter.clone().with_sensors(true)).unwrap();
+        assert_eq!((hit.entity, hit.distance), (sensor, 1.25));
+        let hit = query.cast_ray(Vec2::ZERO, Vec2::X, 10.0, &filter.clone().exclude(near)).unwrap();
+        assert_eq!((hit.entity, hit.distance), (far, 5.0));
+        let all: Vec<_> = query.cast_ray_all(Vec2::ZERO, Vec2::X, 10.0, &filter).iter().map(|hit| hit.entity).collect();
+        assert_eq!(all, vec![near, far]);
+        assert!(query.cast_ray(Vec2::ZERO, Vec2::X, 1.0, &filter).is_none());
+
+        let ball = AbstractShape::Circle { radius: 0.5 };
+        let hit = query.cast_shape(&ball, Vec2::ZERO, 0.0, Vec2::new(10.0, 0.0), &filter).unwrap();
+        assert_eq!(hit.entity, near);
+        assert!((hit.toi - 0.15).abs() < 1e-3);
+        assert!((hit.normal - Vec2::X).length() < 1e-3);
+        assert!(query.cast_shape(&ball, Vec2::ZERO, 0.0, Vec2::new(0.0, 10.0), &filter).is_none());
+    }
+    #[test]
+    fn points_and_regions_find_the_colliders_inside() {
+        let (mut world, [sensor, near, far]) = scene();
+        let mut state: SystemState<PhysicsQuery> = SystemState::new(&mut world);
+        let query = state.get_mut(&mut world);
+        let filter = QueryFilter::default();
+
+        assert_eq!(query.colliders_at_point(Vec2::new(3.5, 0.0), &filter), vec![near]);
+        assert_eq!(query.colliders_at_point(Vec2::new(6.9, 0.9), &filter), vec![far]);
+        // Inside the circle's bounds, but not the circle.
+        assert!(query.colliders_at_point(Vec2::new(3.9, 0.9), &filter).is_empty());
+        assert!(query.colliders_at_point(Vec2::new(1.5, 0.0), &filter).is_empty());
+        assert_eq!(query.colliders_at_point(Vec2::new(1.5, 0.0), &filter.clone().with_sensors(true)), vec![sensor]);
+
+        let ball = AbstractShape::Circle { radius: 0.5 };
+        assert_eq!(query.colliders_overlapping(&ball, Vec2::new(4.6, 0.0), 0.0, &filter), vec![far]);
+        assert!(query.colliders_overlapping(&ball, Vec2::new(4.3, 0.9), 0.0, &filter).is_empty());
+
+        let region = Aabr::new(Point::new(2.5, -0.5), Point::new(5.5, 0.5));
+        assert_eq!(sorted(query.colliders_in_region(&region, &filter)), sorted(vec![near, far]));
+        let region = Aabr::new(Point::new(1.0, -0.5), Point::new(2.2, 0.5));
+        assert_eq!(query.colliders_in_region(&region, &filter), vec![near]);
+        let found = query.colliders_in_region(&region, &filter.clone().with_sensors(true));
+        assert_eq!(sorted(found), sorted(vec![sensor, near]));
+    }
+}
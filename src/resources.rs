@@ -1,3 +1,4 @@
+use bevy::math::Vec2;
 use bevy_inspector_egui::{Inspectable, widgets::ResourceInspector};
 
 use crate::systems::core::ClassicImpulseResolver;
@@ -12,7 +13,31 @@ pub struct Resources {
 #[derive(Debug, Clone, Inspectable)]
 pub struct FishicsConfig {
     pub scale: f32,
+    /// How fast simulated time passes compared to real time.
     pub time: f32,
+    /// Length of a single physics step, in seconds of simulated time.
+    #[inspectable(min = 0.001)]
+    pub timestep: f32,
+    /// The most physics steps allowed in a single frame. Any time left over after this is dropped, rather than letting the
+    /// simulation fall further and further behind.
+    #[inspectable(min = 1)]
+    pub max_steps: u32,
+    /// Each step is split into this many substeps, integrating, finding contacts and resolving them once for each.
+    /// More substeps means fewer missed collisions and stiffer stacks, at the cost of running the solver more often.
+    /// Transforms are only stored and events only sent once per step.
+    #[inspectable(min = 1)]
+    pub substeps: u32,
+    /// Acceleration applied to every body with mass, scaled by its [crate::components::GravityScale].
+    pub gravity: Vec2,
+    /// Bodies slower than this are still enough to fall asleep.
+    #[inspectable(min = 0.0)]
+    pub sleep_linear_threshold: f32,
+    /// Bodies turning slower than this, in radians per second, are still enough to fall asleep.
+    #[inspectable(min = 0.0)]
+    pub sleep_angular_threshold: f32,
+    /// Seconds a body has to stay still before it falls asleep.
+    #[inspectable(min = 0.0)]
+    pub time_to_sleep: f32,
     #[inspectable(ignore)]
     max_speed: f32,
     #[inspectable(ignore)]
@@ -24,6 +49,13 @@ impl Default for FishicsConfig {
         Self {
             scale: 10.0,
             time: 1.0,
+            timestep: 1.0 / 60.0,
+            max_steps: 5,
+            substeps: 1,
+            gravity: Vec2::ZERO,
+            sleep_linear_threshold: 0.1,
+            sleep_angular_threshold: 0.1,
+            time_to_sleep: 0.5,
             max_speed: 0.0,
             max_speed_squared: 0.0,
         }
@@ -31,6 +63,34 @@ impl Default for FishicsConfig {
 }
 
 impl FishicsConfig {
+    pub fn with_gravity(mut self, gravity: Vec2) -> Self {
+        self.gravity = gravity;
+        self
+    }
+
+    pub fn with_timestep(mut self, timestep: f32, max_steps: u32) -> Self {
+        self.timestep = timestep;
+        self.max_steps = max_steps;
+        self
+    }
+
+    pub fn with_substeps(mut self, substeps: u32) -> Self {
+        self.substeps = substeps;
+        self
+    }
+
+    pub fn with_sleeping(mut self, linear_threshold: f32, angular_threshold: f32, time_to_sleep: f32) -> Self {
+        self.sleep_linear_threshold = linear_threshold;
+        self.sleep_angular_threshold = angular_threshold;
+        self.time_to_sleep = time_to_sleep;
+        self
+    }
+
+    /// The length of time each substep covers.
+    pub fn dt(&self) -> f32 {
+        self.timestep / self.substeps.max(1) as f32
+    }
+
     pub fn set_speed_limmit(&mut self, speed_limmit: f32) {
         self.max_speed = speed_limmit;
         self.max_speed_squared = speed_limmit * speed_limmit;
@@ -44,3 +104,120 @@ impl FishicsConfig {
         self.max_speed_squared
     }
 }
+
+/// Keeps track of how much simulated time is waiting to be stepped through.
+#[derive(Debug, Clone, Default)]
+pub struct PhysicsTime {
+    accumulator: f32,
+    steps: u32,
+    frame_steps: u32,
+    substep: u32,
+    looping: bool,
+    alpha: f32,
+}
+
+impl PhysicsTime {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How far between the last two physics steps the current frame is, from 0.0 to 1.0.
+    pub fn alpha(&self) -> f32 {
+        self.alpha
+    }
+
+    /// The number of physics steps taken so far this frame.
+    pub fn steps(&self) -> u32 {
+        self.steps
+    }
+
+    /// The number of whole physics steps this frame will take.
+    pub fn frame_steps(&self) -> u32 {
+        self.frame_steps
+    }
+
+    /// Which substep of the current physics step is running, starting from 0.
+    pub fn substep(&self) -> u32 {
+        self.substep
+    }
+
+    /// Called repeatedly each frame, returning true for as long as another substep should be taken.
+    pub(crate) fn tick(&mut self, delta: f32, timestep: f32, max_steps: u32, substeps: u32) -> bool {
+        if !self.looping {
+            self.accumulator += delta;
+            self.steps = 0;
+            self.frame_steps = if timestep > 0.0 {
+                ((self.accumulator / timestep) as u32).min(max_steps)
+            } else {
+                0
+            };
+        } else if self.substep + 1 < substeps {
+            self.substep += 1;
+            return true;
+        }
+
+        self.substep = 0;
+        if self.accumulator >= timestep && self.steps < max_steps {
+            self.accumulator -= timestep;
+            self.steps += 1;
+            self.looping = true;
+            return true;
+        }
+
+        // Too far behind, so throw the backlog away rather than spiralling.
+        if self.steps >= max_steps {
+            self.accumulator = self.accumulator.min(timestep);
+        }
+        self.looping = false;
+        self.alpha = if timestep > 0.0 { self.accumulator / timestep } else { 0.0 };
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs a whole frame, returning how many times the pipeline ran.
+    fn frame(time: &mut PhysicsTime, delta: f32, timestep: f32, max_steps: u32, substeps: u32) -> u32 {
+        let mut runs = 0;
+        while time.tick(delta, timestep, max_steps, substeps) {
+            assert_eq!(time.steps(), runs / substeps + 1);
+            assert_eq!(time.substep(), runs % substeps);
+            runs += 1;
+        }
+        runs
+    }
+
+    #[test]
+    fn leftover_time_carries_into_the_next_frame() {
+        let mut time = PhysicsTime::new();
+        assert_eq!(frame(&mut time, 0.625, 0.25, 8, 1), 2);
+        assert_eq!(time.alpha(), 0.5);
+
+        assert_eq!(frame(&mut time, 0.125, 0.25, 8, 1), 1);
+        assert_eq!(time.alpha(), 0.0);
+    }
+
+    #[test]
+    fn falling_behind_drops_the_backlog() {
+        let mut time = PhysicsTime::new();
+        assert_eq!(frame(&mut time, 2.0, 0.25, 3, 1), 3);
+        assert_eq!(time.alpha(), 1.0);
+
+        // Only the one step that was kept is left over.
+        assert_eq!(frame(&mut time, 0.0, 0.25, 3, 1), 1);
+    }
+
+    #[test]
+    fn substeps_split_every_step() {
+        let mut time = PhysicsTime::new();
+        assert_eq!(frame(&mut time, 0.625, 0.25, 8, 4), 8);
+        assert_eq!(time.steps(), 2);
+        assert_eq!(time.alpha(), 0.5);
+
+        // The step limit counts whole steps, not substeps.
+        assert_eq!(frame(&mut time, 2.0, 0.25, 3, 4), 12);
+        assert_eq!(time.steps(), 3);
+    }
+}
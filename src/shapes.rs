@@ -1,21 +1,57 @@
+use std::borrow::Cow;
+
 use bevy::math::Vec2;
 use bevy_inspector_egui::Inspectable;
 use prima::prelude::*;
 
+use crate::{
+    cast,
+    contact::{self, Contact},
+};
+
 /// A wrapper for all valid shapes.
 #[derive(Debug, Clone)]
 pub enum ShapeWrapper {
     Circle(Circle<f32>),
     Aabr(Aabr<f32>),
+    Obb(Obb),
     Line(Line<f32>),
+    Segment(Segment),
+    /// A convex polygon, as world space vertices wound anti-clockwise.
+    Polygon(Vec<Vec2>),
+}
+
+/// A line with rounded ends, thickened out by its radius. Capsules and lines with any thickness end up as one of these.
+#[derive(Debug, Clone, Copy)]
+pub struct Segment {
+    pub start: Vec2,
+    pub end: Vec2,
+    pub radius: f32,
+}
+
+/// A rectangle that has been turned about its center. Rectangle colliders on a rotated body end up as one of these.
+#[derive(Debug, Clone, Copy)]
+pub struct Obb {
+    pub center: Vec2,
+    /// Half the width and height, before rotation.
+    pub half_extents: Vec2,
+    /// Rotation in radians, anti-clockwise.
+    pub rotation: f32,
 }
 
 /// Just stores the shapes extremities without positional data.
-#[derive(Debug, Clone, Copy, Inspectable)]
+#[derive(Debug, Clone, Inspectable)]
 pub enum AbstractShape {
     Circle { radius: f32 },
     Aabr { width: f32, height: f32 },
-    Line { start: Vec2, end: Vec2 },
+    /// A line between two points relative to the body. Thickness rounds it out evenly on both sides and around the ends.
+    Line { start: Vec2, end: Vec2, thickness: f32 },
+    /// A pill standing upright on the body's position. The half height is to the centers of the rounded ends, so the
+    /// whole capsule is `2 * (half_height + radius)` tall.
+    Capsule { half_height: f32, radius: f32 },
+    /// A convex polygon around the body's position, wound anti-clockwise. Use [AbstractShape::polygon] to build one from
+    /// loose points. Vertices that are not convex are wrapped in their hull wherever the shape is used.
+    Polygon { vertices: Vec<Vec2> },
 }
 
 impl ShapeWrapper {
@@ -27,12 +63,242 @@ impl ShapeWrapper {
         Self::Aabr(Aabr::from_point(center, width, height))
     }
 
+    pub fn obb(center: Point<f32>, width: f32, height: f32, rotation: f32) -> Self {
+        Self::Obb(Obb::new(Vec2::new(center.x, center.y), width, height, rotation))
+    }
+
     pub fn line(start: Vec2, end: Vec2) -> Self {
         Self::Line(Line::new(
             Point::new(start.x, start.y),
             Point::new(end.x, end.y),
         ))
     }
+
+    pub fn segment(start: Vec2, end: Vec2, radius: f32) -> Self {
+        Self::Segment(Segment { start, end, radius })
+    }
+
+    /// Every point of contact between two shapes, with the normal pointing from this shape towards the other. Rectangles
+    /// and polygons are clipped against each other to find up to two points, everything else gives a single point.
+    pub fn contact(&self, other: &Self) -> Option<Contact> {
+        match (self, other) {
+            (ShapeWrapper::Circle(circle), ShapeWrapper::Obb(obb)) => {
+                contact::circle_obb(Vec2::new(circle.center.x, circle.center.y), circle.radius, obb)
+            }
+            (ShapeWrapper::Obb(obb), ShapeWrapper::Circle(circle)) => {
+                contact::circle_obb(Vec2::new(circle.center.x, circle.center.y), circle.radius, obb).map(Contact::flipped)
+            }
+            (ShapeWrapper::Circle(circle), ShapeWrapper::Polygon(vertices)) => {
+                contact::circle_polygon(Vec2::new(circle.center.x, circle.center.y), circle.radius, vertices)
+            }
+            (ShapeWrapper::Polygon(vertices), ShapeWrapper::Circle(circle)) => {
+                contact::circle_polygon(Vec2::new(circle.center.x, circle.center.y), circle.radius, vertices)
+                    .map(Contact::flipped)
+            }
+            (ShapeWrapper::Circle(circle), ShapeWrapper::Aabr(aabr)) => contact::circle_polygon(
+                Vec2::new(circle.center.x, circle.center.y),
+                circle.radius,
+                &contact::aabr_vertices(aabr),
+            ),
+            (ShapeWrapper::Aabr(aabr), ShapeWrapper::Circle(circle)) => contact::circle_polygon(
+                Vec2::new(circle.center.x, circle.center.y),
+                circle.radius,
+                &contact::aabr_vertices(aabr),
+            )
+            .map(Contact::flipped),
+            (
+                ShapeWrapper::Aabr(_) | ShapeWrapper::Obb(_) | ShapeWrapper::Polygon(_),
+                ShapeWrapper::Aabr(_) | ShapeWrapper::Obb(_) | ShapeWrapper::Polygon(_),
+            ) => contact::polygon_polygon(&self.vertices(), &other.vertices()),
+            // Everything left has something round or a bare line in it, which all go through the segment tests.
+            // Circles are segments that go nowhere.
+            _ => match (self.as_segment(), other.as_segment()) {
+                (Some((a, a_radius)), Some((b, b_radius))) => contact::segment_segment(a, a_radius, b, b_radius),
+                (Some((a, radius)), None) => contact::segment_polygon(a, radius, &other.vertices()),
+                (None, Some((b, radius))) => contact::segment_polygon(b, radius, &self.vertices()).map(Contact::flipped),
+                (None, None) => None,
+            },
+        }
+    }
+
+    /// How far along a ray the shape is first hit, and the surface normal there. The direction should be normalized, and
+    /// rays that start inside the shape hit straight away.
+    pub fn cast_ray(&self, origin: Vec2, direction: Vec2, max: f32) -> Option<(f32, Vec2)> {
+        match self {
+            ShapeWrapper::Circle(circle) => {
+                cast::ray_circle(Vec2::new(circle.center.x, circle.center.y), circle.radius, origin, direction, max)
+            }
+            ShapeWrapper::Line(line) => cast::ray_segment(
+                Vec2::new(line.start.x, line.start.y),
+                Vec2::new(line.end.x, line.end.y),
+                origin,
+                direction,
+                max,
+            ),
+            ShapeWrapper::Segment(segment) => {
+                cast::ray_rounded_segment(segment.start, segment.end, segment.radius, origin, direction, max)
+            }
+            _ => cast::ray_polygon(&self.vertices(), origin, direction, max),
+        }
+    }
+
+    /// True if the point is inside the shape or on its edge. Lines without any thickness have no inside to speak of.
+    pub fn contains_point(&self, point: Vec2) -> bool {
+        match self {
+            ShapeWrapper::Circle(circle) => {
+                Vec2::new(circle.center.x, circle.center.y).distance_squared(point) <= circle.radius * circle.radius
+            }
+            ShapeWrapper::Aabr(aabr) => {
+                point.x >= aabr.min.x && point.x <= aabr.max.x && point.y >= aabr.min.y && point.y <= aabr.max.y
+            }
+            ShapeWrapper::Obb(obb) => obb.contains(point),
+            ShapeWrapper::Line(_) => false,
+            ShapeWrapper::Segment(segment) => {
+                contact::closest_on_segment(segment.start, segment.end, point).distance(point) <= segment.radius
+            }
+            ShapeWrapper::Polygon(vertices) => contact::polygon_separation(vertices, point) <= 0.0,
+        }
+    }
+
+    /// The corners of the shape, wound anti-clockwise. Lines give their two ends, and circles have none.
+    pub fn vertices(&self) -> Vec<Vec2> {
+        match self {
+            ShapeWrapper::Circle(_) => Vec::new(),
+            ShapeWrapper::Aabr(aabr) => contact::aabr_vertices(aabr).to_vec(),
+            ShapeWrapper::Obb(obb) => obb.vertices().to_vec(),
+            ShapeWrapper::Line(line) => vec![
+                Vec2::new(line.start.x, line.start.y),
+                Vec2::new(line.end.x, line.end.y),
+            ],
+            ShapeWrapper::Segment(segment) => vec![segment.start, segment.end],
+            ShapeWrapper::Polygon(vertices) => vertices.clone(),
+        }
+    }
+
+    /// The shape as a bare core and how far it is rounded out from it. Circles have a single point at their center, and
+    /// everything with corners has no rounding at all.
+    pub fn outline(&self) -> (Vec<Vec2>, f32) {
+        match self {
+            ShapeWrapper::Circle(circle) => (vec![Vec2::new(circle.center.x, circle.center.y)], circle.radius),
+            ShapeWrapper::Segment(segment) => (vec![segment.start, segment.end], segment.radius),
+            _ => (self.vertices(), 0.0),
+        }
+    }
+
+    /// The smallest axis aligned rectangle that holds the whole shape.
+    pub fn bounding_rect(&self) -> Aabr<f32> {
+        match self {
+            ShapeWrapper::Circle(circle) => circle.bounding_rect(),
+            ShapeWrapper::Aabr(aabr) => *aabr,
+            ShapeWrapper::Obb(obb) => obb.bounding_rect(),
+            _ => {
+                let (core, radius) = self.outline();
+                let bounds = bounding_rect(&core);
+                Aabr::new(
+                    Point::new(bounds.min.x - radius, bounds.min.y - radius),
+                    Point::new(bounds.max.x + radius, bounds.max.y + radius),
+                )
+            }
+        }
+    }
+
+    /// Circles, lines and segments as a segment and a radius.
+    fn as_segment(&self) -> Option<([Vec2; 2], f32)> {
+        match self {
+            ShapeWrapper::Circle(circle) => {
+                let center = Vec2::new(circle.center.x, circle.center.y);
+                Some(([center, center], circle.radius))
+            }
+            ShapeWrapper::Line(line) => Some((
+                [Vec2::new(line.start.x, line.start.y), Vec2::new(line.end.x, line.end.y)],
+                0.0,
+            )),
+            ShapeWrapper::Segment(segment) => Some(([segment.start, segment.end], segment.radius)),
+            _ => None,
+        }
+    }
+
+    /// True if every point of the other shape is inside this one. Only works for shapes without rounded edges, which
+    /// are convex so it is enough to check the other shape's core with its rounding as a margin.
+    fn holds(&self, other: &Self) -> bool {
+        let (core, radius) = other.outline();
+        match self {
+            ShapeWrapper::Circle(circle) => core.iter().all(|v| {
+                Vec2::new(v.x - circle.center.x, v.y - circle.center.y).length() + radius <= circle.radius
+            }),
+            ShapeWrapper::Aabr(aabr) => core.iter().all(|v| {
+                v.x - radius >= aabr.min.x
+                    && v.x + radius <= aabr.max.x
+                    && v.y - radius >= aabr.min.y
+                    && v.y + radius <= aabr.max.y
+            }),
+            ShapeWrapper::Obb(obb) => core.iter().all(|v| {
+                let local = obb.to_local(*v).abs();
+                local.x + radius <= obb.half_extents.x && local.y + radius <= obb.half_extents.y
+            }),
+            ShapeWrapper::Segment(segment) => core.iter().all(|v| {
+                contact::closest_on_segment(segment.start, segment.end, *v).distance(*v) + radius <= segment.radius
+            }),
+            ShapeWrapper::Polygon(vertices) => {
+                core.iter().all(|v| contact::polygon_separation(vertices, *v) <= -radius)
+            }
+            ShapeWrapper::Line(_) => false,
+        }
+    }
+}
+
+impl Obb {
+    pub fn new(center: Vec2, width: f32, height: f32, rotation: f32) -> Self {
+        Self {
+            center,
+            half_extents: Vec2::new(width / 2.0, height / 2.0),
+            rotation,
+        }
+    }
+
+    /// The box's local x and y axes in world space.
+    pub fn axes(&self) -> (Vec2, Vec2) {
+        let (sin, cos) = self.rotation.sin_cos();
+        (Vec2::new(cos, sin), Vec2::new(-sin, cos))
+    }
+
+    /// The corners of the box, wound anti-clockwise.
+    pub fn vertices(&self) -> [Vec2; 4] {
+        let (x_axis, y_axis) = self.axes();
+        let x = x_axis * self.half_extents.x;
+        let y = y_axis * self.half_extents.y;
+        [
+            self.center - x - y,
+            self.center + x - y,
+            self.center + x + y,
+            self.center - x + y,
+        ]
+    }
+
+    /// Moves a world space point into the box's own space, where it is axis aligned and centered on the origin.
+    pub fn to_local(&self, point: Vec2) -> Vec2 {
+        let (x_axis, y_axis) = self.axes();
+        let offset = point - self.center;
+        Vec2::new(offset.dot(x_axis), offset.dot(y_axis))
+    }
+
+    pub fn contains(&self, point: Vec2) -> bool {
+        let local = self.to_local(point).abs();
+        local.x <= self.half_extents.x && local.y <= self.half_extents.y
+    }
+
+    /// The smallest axis aligned rectangle that holds the whole box.
+    pub fn bounding_rect(&self) -> Aabr<f32> {
+        let (sin, cos) = self.rotation.sin_cos();
+        let (sin, cos) = (sin.abs(), cos.abs());
+        let width = self.half_extents.x * 2.0;
+        let height = self.half_extents.y * 2.0;
+        Aabr::from_point(
+            Point::new(self.center.x, self.center.y),
+            width * cos + height * sin,
+            width * sin + height * cos,
+        )
+    }
 }
 
 impl AbstractShape {
@@ -45,17 +311,152 @@ impl AbstractShape {
     }
 
     pub fn line(start: Vec2, end: Vec2) -> Self {
-        Self::Line { start, end }
+        Self::Line { start, end, thickness: 0.0 }
     }
 
-    pub fn wrap(self, position: Point<f32>) -> ShapeWrapper {
-        match self {
+    pub fn thick_line(start: Vec2, end: Vec2, thickness: f32) -> Self {
+        Self::Line { start, end, thickness }
+    }
+
+    pub fn capsule(half_height: f32, radius: f32) -> Self {
+        Self::Capsule { half_height, radius }
+    }
+
+    /// Builds a polygon from the convex hull of the given points, so they can be in any order and any inward points are
+    /// dropped. Returns None if the points do not enclose any area.
+    pub fn polygon(points: &[Vec2]) -> Option<Self> {
+        let vertices = convex_hull(points);
+        if vertices.len() < 3 {
+            return None;
+        }
+        Some(Self::Polygon { vertices })
+    }
+
+    /// The surface area of the shape. Lines without thickness have no area.
+    pub fn area(&self) -> f32 {
+        match *self {
+            AbstractShape::Circle { radius } => std::f32::consts::PI * radius * radius,
+            AbstractShape::Aabr { width, height } => width * height,
+            AbstractShape::Line { start, end, thickness } => {
+                let radius = thickness / 2.0;
+                (end - start).length() * thickness + std::f32::consts::PI * radius * radius
+            }
+            AbstractShape::Capsule { half_height, radius } => {
+                4.0 * half_height * radius + std::f32::consts::PI * radius * radius
+            }
+            AbstractShape::Polygon { ref vertices } => {
+                let twice_area: f32 = edges(&convex_vertices(vertices)).map(|(a, b)| a.perp_dot(b)).sum();
+                twice_area / 2.0
+            }
+        }
+    }
+
+    /// The center of the shape's area, relative to the body. Only lines and polygons can sit off the body's position.
+    pub fn centroid(&self) -> Vec2 {
+        match *self {
+            AbstractShape::Line { start, end, .. } => (start + end) / 2.0,
+            AbstractShape::Polygon { ref vertices } => {
+                let vertices = convex_vertices(vertices);
+                let mut sum = Vec2::ZERO;
+                let mut twice_area = 0.0;
+                for (a, b) in edges(&vertices) {
+                    let cross = a.perp_dot(b);
+                    sum += (a + b) * cross;
+                    twice_area += cross;
+                }
+                if twice_area > 0.0 {
+                    sum / (3.0 * twice_area)
+                } else {
+                    Vec2::ZERO
+                }
+            }
+            _ => Vec2::ZERO,
+        }
+    }
+
+    /// The moment of inertia of the shape about its [AbstractShape::centroid], for the given mass.
+    pub fn inertia(&self, mass: f32) -> f32 {
+        match *self {
+            AbstractShape::Circle { radius } => mass * radius * radius / 2.0,
+            AbstractShape::Aabr { width, height } => mass * (width * width + height * height) / 12.0,
+            AbstractShape::Line { start, end, thickness } => {
+                mass * ((end - start).length_squared() + thickness * thickness) / 12.0
+            }
+            AbstractShape::Capsule { half_height, radius } => {
+                // Split the mass between the middle rectangle and the two rounded ends by area.
+                let rect_area = 4.0 * half_height * radius;
+                let cap_area = std::f32::consts::PI * radius * radius;
+                let total = rect_area + cap_area;
+                if total <= 0.0 {
+                    return 0.0;
+                }
+                let rect_mass = mass * rect_area / total;
+                let cap_mass = mass * cap_area / total;
+
+                // Each rounded end is half a disc, moved out to the end of the middle section.
+                let centroid = 4.0 * radius / (3.0 * std::f32::consts::PI);
+                let rect = rect_mass * (4.0 * radius * radius + 4.0 * half_height * half_height) / 12.0;
+                let caps = cap_mass * (radius * radius / 2.0 + half_height * half_height + 2.0 * half_height * centroid);
+                rect + caps
+            }
+            AbstractShape::Polygon { ref vertices } => {
+                // Sum up the triangles fanning out from the centroid, weighted by their area.
+                let centroid = self.centroid();
+                let mut numerator = 0.0;
+                let mut denominator = 0.0;
+                for (a, b) in edges(&convex_vertices(vertices)) {
+                    let (a, b) = (a - centroid, b - centroid);
+                    let cross = a.perp_dot(b);
+                    numerator += cross * (a.dot(a) + a.dot(b) + b.dot(b));
+                    denominator += cross;
+                }
+                if denominator > 0.0 {
+                    mass * numerator / (6.0 * denominator)
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+
+    /// Places the shape in the world, turning it about the position by the rotation in radians. Rectangles only become
+    /// [Obb]s if they are not already lined up with the axes.
+    pub fn wrap(&self, position: Point<f32>, rotation: f32) -> ShapeWrapper {
+        match *self {
             AbstractShape::Circle { radius } => ShapeWrapper::circle(position, radius),
-            AbstractShape::Aabr { width, height } => ShapeWrapper::aabr(position, width, height),
-            AbstractShape::Line { start, end } => ShapeWrapper::line(
-                Vec2::new(position.x, position.y) + start,
-                Vec2::new(position.x, position.y) + end,
-            ),
+            AbstractShape::Aabr { width, height } => {
+                if rotation.sin().abs() <= f32::EPSILON {
+                    ShapeWrapper::aabr(position, width, height)
+                } else {
+                    ShapeWrapper::obb(position, width, height, rotation)
+                }
+            }
+            AbstractShape::Line { start, end, thickness } => {
+                let offset = Vec2::new(position.x, position.y);
+                let start = offset + turn(start, rotation);
+                let end = offset + turn(end, rotation);
+                if thickness > 0.0 {
+                    ShapeWrapper::segment(start, end, thickness / 2.0)
+                } else {
+                    ShapeWrapper::line(start, end)
+                }
+            }
+            AbstractShape::Capsule { half_height, radius } => {
+                let offset = Vec2::new(position.x, position.y);
+                let up = turn(Vec2::new(0.0, half_height), rotation);
+                ShapeWrapper::segment(offset - up, offset + up, radius)
+            }
+            AbstractShape::Polygon { ref vertices } => {
+                // Vertices edited by hand might not be convex any more, and the narrow phase relies on it.
+                let offset = Vec2::new(position.x, position.y);
+                let vertices: Vec<Vec2> = convex_vertices(vertices).iter().map(|v| offset + turn(*v, rotation)).collect();
+                match vertices.len() {
+                    0 => ShapeWrapper::circle(position, 0.0),
+                    1 => ShapeWrapper::circle(Point::new(vertices[0].x, vertices[0].y), 0.0),
+                    2 => ShapeWrapper::line(vertices[0], vertices[1]),
+                    _ => ShapeWrapper::Polygon(vertices),
+                }
+            }
         }
     }
 }
@@ -65,33 +466,99 @@ impl Into<AbstractShape> for ShapeWrapper {
         match self {
             ShapeWrapper::Circle(circle) => AbstractShape::circle(circle.radius),
             ShapeWrapper::Aabr(aabr) => AbstractShape::aabr(aabr.width(), aabr.height()),
+            ShapeWrapper::Obb(obb) => AbstractShape::aabr(obb.half_extents.x * 2.0, obb.half_extents.y * 2.0),
             ShapeWrapper::Line(line) => AbstractShape::line(
                 Vec2::new(line.start.x, line.start.y),
                 Vec2::new(line.end.x, line.end.y),
             ),
+            ShapeWrapper::Segment(segment) => AbstractShape::thick_line(segment.start, segment.end, segment.radius * 2.0),
+            ShapeWrapper::Polygon(vertices) => AbstractShape::Polygon { vertices },
+        }
+    }
+}
+
+/// Turns a point about the origin, anti-clockwise by the rotation in radians.
+pub(crate) fn turn(point: Vec2, rotation: f32) -> Vec2 {
+    let (sin, cos) = rotation.sin_cos();
+    Vec2::new(point.x * cos - point.y * sin, point.x * sin + point.y * cos)
+}
+
+/// Wraps the points in the smallest convex polygon that holds them all, wound anti-clockwise. Points that lie along an
+/// edge are dropped.
+pub fn convex_hull(points: &[Vec2]) -> Vec<Vec2> {
+    let mut points = points.to_vec();
+    points.sort_by(|a, b| {
+        a.x.partial_cmp(&b.x)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then(a.y.partial_cmp(&b.y).unwrap_or(std::cmp::Ordering::Equal))
+    });
+    points.dedup();
+    if points.len() < 3 {
+        return points;
+    }
+
+    // Andrew's monotone chain: build the lower half going right, then the upper half coming back.
+    let mut hull: Vec<Vec2> = Vec::with_capacity(points.len() * 2);
+    for pass in 0..2 {
+        let start = hull.len();
+        for point in points.iter() {
+            while hull.len() >= start + 2 {
+                let a = hull[hull.len() - 2];
+                let b = hull[hull.len() - 1];
+                if (b - a).perp_dot(*point - a) > 0.0 {
+                    break;
+                }
+                hull.pop();
+            }
+            hull.push(*point);
+        }
+        // The last point of each half is the first point of the next.
+        hull.pop();
+        if pass == 0 {
+            points.reverse();
         }
     }
+    hull
+}
+
+/// True if the vertices make a convex polygon wound anti-clockwise, with no three in a line.
+pub fn is_convex(vertices: &[Vec2]) -> bool {
+    vertices.len() >= 3
+        && (0..vertices.len()).all(|i| {
+            let a = vertices[i];
+            let b = vertices[(i + 1) % vertices.len()];
+            let c = vertices[(i + 2) % vertices.len()];
+            (b - a).perp_dot(c - b) > 0.0
+        })
+}
+
+/// The vertices as they are if they already make a convex polygon wound anti-clockwise, otherwise their
+/// [convex_hull].
+pub fn convex_vertices(vertices: &[Vec2]) -> Cow<'_, [Vec2]> {
+    if is_convex(vertices) {
+        Cow::Borrowed(vertices)
+    } else {
+        Cow::Owned(convex_hull(vertices))
+    }
+}
+
+/// The smallest axis aligned rectangle that holds every point.
+pub fn bounding_rect(points: &[Vec2]) -> Aabr<f32> {
+    let min = points.iter().fold(Vec2::splat(f32::MAX), |min, p| min.min(*p));
+    let max = points.iter().fold(Vec2::splat(f32::MIN), |max, p| max.max(*p));
+    Aabr::new(Point::new(min.x, min.y), Point::new(max.x, max.y))
+}
+
+/// Every edge of a closed polygon, as pairs of vertices.
+fn edges(vertices: &[Vec2]) -> impl Iterator<Item = (Vec2, Vec2)> + '_ {
+    (0..vertices.len()).map(move |i| (vertices[i], vertices[(i + 1) % vertices.len()]))
 }
 
 impl Collide<f32> for ShapeWrapper {
+    /// Goes through [ShapeWrapper::contact], so the normal always points from this shape towards the other whatever the
+    /// pair of shapes is.
     fn collision(&self, other: &Self) -> Option<Collision<f32>> {
-        match self {
-            ShapeWrapper::Circle(self_circle) => match other {
-                ShapeWrapper::Circle(other_circle) => self_circle.collision(other_circle),
-                ShapeWrapper::Aabr(other_aabr) => self_circle.collision(other_aabr),
-                ShapeWrapper::Line(other_line) => self_circle.collision(other_line),
-            },
-            ShapeWrapper::Aabr(self_aabr) => match other {
-                ShapeWrapper::Circle(other_circle) => other_circle.collision(self_aabr),
-                ShapeWrapper::Aabr(other_aabr) => self_aabr.collision(other_aabr),
-                ShapeWrapper::Line(other_line) => self_aabr.collision(other_line),
-            },
-            ShapeWrapper::Line(self_line) => match other {
-                ShapeWrapper::Circle(other_circle) => other_circle.collision(self_line),
-                ShapeWrapper::Aabr(other_aabr) => other_aabr.collision(self_line),
-                ShapeWrapper::Line(_) => None,
-            },
-        }
+        self.contact(other).and_then(|contact| contact.collision())
     }
 
     fn enveloping(&self, other: &Self) -> bool {
@@ -100,17 +567,20 @@ impl Collide<f32> for ShapeWrapper {
                 ShapeWrapper::Circle(other_circle) => self_circle.enveloping(other_circle),
                 ShapeWrapper::Aabr(other_aabr) => self_circle.enveloping(other_aabr),
                 ShapeWrapper::Line(other_line) => self_circle.enveloping(other_line),
+                _ => self.holds(other),
             },
             ShapeWrapper::Aabr(self_aabr) => match other {
                 ShapeWrapper::Circle(other_circle) => other_circle.enveloping(self_aabr),
                 ShapeWrapper::Aabr(other_aabr) => self_aabr.enveloping(other_aabr),
                 ShapeWrapper::Line(other_line) => self_aabr.enveloping(other_line),
+                _ => self.holds(other),
             },
             ShapeWrapper::Line(self_line) => match other {
                 ShapeWrapper::Circle(other_circle) => other_circle.enveloping(self_line),
                 ShapeWrapper::Aabr(other_aabr) => other_aabr.enveloping(self_line),
-                ShapeWrapper::Line(_) => false,
+                _ => false,
             },
+            _ => self.holds(other),
         }
     }
 
@@ -120,17 +590,134 @@ impl Collide<f32> for ShapeWrapper {
                 ShapeWrapper::Circle(other_circle) => self_circle.enveloped_by(other_circle),
                 ShapeWrapper::Aabr(other_aabr) => self_circle.enveloped_by(other_aabr),
                 ShapeWrapper::Line(other_line) => self_circle.enveloped_by(other_line),
+                _ => other.enveloping(self),
             },
             ShapeWrapper::Aabr(self_aabr) => match other {
                 ShapeWrapper::Circle(other_circle) => other_circle.enveloped_by(self_aabr),
                 ShapeWrapper::Aabr(other_aabr) => self_aabr.enveloped_by(other_aabr),
                 ShapeWrapper::Line(other_line) => self_aabr.enveloped_by(other_line),
+                _ => other.enveloping(self),
             },
             ShapeWrapper::Line(self_line) => match other {
                 ShapeWrapper::Circle(other_circle) => other_circle.enveloped_by(self_line),
                 ShapeWrapper::Aabr(other_aabr) => other_aabr.enveloped_by(self_line),
-                ShapeWrapper::Line(_) => false,
+                _ => other.enveloping(self),
             },
+            _ => other.enveloping(self),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-4
+    }
+
+    fn signed_area(vertices: &[Vec2]) -> f32 {
+        edges(vertices).map(|(a, b)| a.perp_dot(b)).sum::<f32>() / 2.0
+    }
+
+    /// Checks the normal is unit length and points from `a` towards `b`, whichever way round they are asked.
+    fn assert_points_across(a: &ShapeWrapper, b: &ShapeWrapper, towards: Vec2) {
+        let forward = a.contact(b).unwrap().normal;
+        let backward = b.contact(a).unwrap().normal;
+        assert!((forward.length() - 1.0).abs() < 1e-4);
+        assert!(forward.dot(towards) > 0.0);
+        assert!((forward + backward).length() < 1e-4);
+    }
+
+    #[test]
+    fn obb_obb_normals_point_from_self_to_other() {
+        let a = ShapeWrapper::obb(Point::new(0.0, 0.0), 2.0, 2.0, 0.3);
+        let b = ShapeWrapper::obb(Point::new(1.8, 0.4), 2.0, 1.0, -0.5);
+        assert_points_across(&a, &b, Vec2::new(1.8, 0.4));
+    }
+
+    #[test]
+    fn obb_circle_normals_point_from_self_to_other() {
+        let obb = ShapeWrapper::obb(Point::new(0.0, 0.0), 2.0, 2.0, 0.3);
+        let circle = ShapeWrapper::circle(Point::new(1.5, 0.0), 0.7);
+        assert_points_across(&obb, &circle, Vec2::X);
+        assert_points_across(&circle, &obb, -Vec2::X);
+    }
+
+    #[test]
+    fn hull_drops_inner_and_edge_points() {
+        let points = [
+            Vec2::new(1.0, 1.0),
+            Vec2::new(0.0, 0.0),
+            Vec2::new(2.0, 0.0),
+            Vec2::new(1.0, 0.0),
+            Vec2::new(2.0, 2.0),
+            Vec2::new(0.0, 2.0),
+        ];
+        let hull = convex_hull(&points);
+
+        assert_eq!(hull.len(), 4);
+        assert!(!hull.contains(&Vec2::new(1.0, 1.0)));
+        assert!(!hull.contains(&Vec2::new(1.0, 0.0)));
+        assert!(is_convex(&hull));
+        assert!(close(signed_area(&hull), 4.0));
+    }
+
+    #[test]
+    fn hull_winds_clockwise_input_anticlockwise() {
+        let points = [Vec2::new(0.0, 0.0), Vec2::new(0.0, 1.0), Vec2::new(1.0, 0.0)];
+        assert!(!is_convex(&points));
+        let hull = convex_hull(&points);
+        assert!(is_convex(&hull));
+        assert!(close(signed_area(&hull), 0.5));
+    }
+
+    #[test]
+    fn hull_of_a_line_is_its_ends() {
+        let points = [Vec2::new(0.0, 0.0), Vec2::new(2.0, 2.0), Vec2::new(1.0, 1.0), Vec2::new(2.0, 2.0)];
+        let hull = convex_hull(&points);
+        assert_eq!(hull.len(), 2);
+        assert!(hull.contains(&Vec2::new(0.0, 0.0)) && hull.contains(&Vec2::new(2.0, 2.0)));
+        assert!(AbstractShape::polygon(&points).is_none());
+    }
+
+    #[test]
+    fn off_center_polygon_spins_about_its_centroid() {
+        let square = AbstractShape::Polygon {
+            vertices: vec![Vec2::new(0.0, 0.0), Vec2::new(2.0, 0.0), Vec2::new(2.0, 2.0), Vec2::new(0.0, 2.0)],
+        };
+        let centroid = square.centroid();
+        assert!(close(centroid.x, 1.0) && close(centroid.y, 1.0));
+        assert!(close(square.area(), 4.0));
+        assert!(close(square.inertia(3.0), AbstractShape::aabr(2.0, 2.0).inertia(3.0)));
+    }
+
+    #[test]
+    fn clockwise_polygon_is_hulled_when_used() {
+        let square = AbstractShape::Polygon {
+            vertices: vec![Vec2::new(-1.0, -1.0), Vec2::new(-1.0, 1.0), Vec2::new(1.0, 1.0), Vec2::new(1.0, -1.0)],
+        };
+        assert!(close(square.area(), 4.0));
+        match square.wrap(Point::new(0.0, 0.0), 0.0) {
+            ShapeWrapper::Polygon(vertices) => assert!(is_convex(&vertices)),
+            other => panic!("expected a polygon, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn capsules_collide_along_their_sides() {
+        let capsule = AbstractShape::capsule(1.0, 0.5);
+        let a = capsule.wrap(Point::new(0.0, 0.0), 0.0);
+        let b = capsule.wrap(Point::new(0.9, 0.5), 0.0);
+        let contact = a.contact(&b).unwrap();
+        assert!(close(contact.normal.x, 1.0) && close(contact.normal.y, 0.0));
+        assert!(close(contact.points[0].depth, 0.1));
+
+        // Laid on its side, a ball resting on top only sinks into the rounding.
+        let lying = capsule.wrap(Point::new(0.0, 0.0), std::f32::consts::FRAC_PI_2);
+        let ball = ShapeWrapper::circle(Point::new(0.3, 0.9), 0.5);
+        let contact = lying.contact(&ball).unwrap();
+        assert!(close(contact.normal.x, 0.0) && close(contact.normal.y, 1.0));
+        assert!(close(contact.points[0].depth, 0.1));
+    }
+}
@@ -1,29 +1,47 @@
 use bevy::prelude::*;
-use prima::prelude::*;
 
-use crate::{components::{RigidBody, Collider}, pipeline::BroadPhasePairs};
+use crate::{broad_phase::BroadPhase, components::{BodyType, RigidBody, Collider, CompoundCollider, Sleep}, pipeline::{BroadPhasePairs, PendingRemovals}};
 
+use super::{is_idle, BodyColliders};
 
 /// Find potential collisions.
-pub fn broad_phase(
+pub fn broad_phase<B>(
     mut bf_pairs: ResMut<BroadPhasePairs>,
-    bodies: Query<(Entity, &RigidBody, &Collider)>,
-) {
-    let mut pairs = Vec::new();
-
-    // TODO: Quad trees!!!
-
-    let mut combos = bodies.iter_combinations();
-    while let Some([(entity_a, rb_a, c_a), (entity_b, rb_b, c_b)]) = combos.next() {
-        if c_a.layer & c_b.layer == 0 {
-            continue;
+    mut broad: ResMut<B>,
+    colliders: BodyColliders,
+    bodies: Query<&RigidBody>,
+    types: Query<&BodyType>,
+    sleeping: Query<&Sleep>,
+    added: Query<Entity, (With<RigidBody>, Or<(Added<RigidBody>, Added<Collider>, Added<CompoundCollider>)>)>,
+    // Children can move or change without their parent hearing about it, so compound bodies are always refreshed.
+    changed: Query<Entity, (With<RigidBody>, Or<(Changed<RigidBody>, Changed<Collider>, Changed<CompoundCollider>, With<Children>)>)>,
+    mut removals: ResMut<PendingRemovals>,
+) where
+    B: BroadPhase,
+{
+    for entity in removals.0.drain(..) {
+        // Losing one collider does not mean a compound body has nothing left to collide with.
+        match bodies.get(entity).ok().and_then(|rb| colliders.bounds(entity, rb)) {
+            Some((bounds, groups)) => broad.update(entity, bounds, groups),
+            None => broad.remove(entity),
         }
-
-        let b_a = c_a.global_aabr(rb_a);
-        let b_b = c_b.global_aabr(rb_b);
-        if b_a.intersecting(&b_b) {
-            pairs.push((entity_a, entity_b));
+    }
+    for entity in added.iter() {
+        if let Some((bounds, groups)) = bodies.get(entity).ok().and_then(|rb| colliders.bounds(entity, rb)) {
+            broad.insert(entity, bounds, groups);
         }
     }
-    bf_pairs.pairs = pairs;
-}
\ No newline at end of file
+    for entity in changed.iter() {
+        match bodies.get(entity).ok().and_then(|rb| colliders.bounds(entity, rb)) {
+            Some((bounds, groups)) => broad.update(entity, bounds, groups),
+            None => broad.remove(entity),
+        }
+    }
+
+    // Static and sleeping bodies can never do anything to each other, so they only need pairing with something awake.
+    bf_pairs.pairs = broad
+        .pairs()
+        .into_iter()
+        .filter(|(a, b)| !(is_idle(*a, &types, &sleeping) && is_idle(*b, &types, &sleeping)))
+        .collect();
+}
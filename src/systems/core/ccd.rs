@@ -0,0 +1,138 @@
+use bevy::prelude::*;
+use prima::prelude::*;
+
+use crate::{
+    broad_phase::BroadPhase,
+    cast,
+    components::{BodyType, Ccd, PreviousRigidBody, RigidBody, Sensor},
+};
+
+use super::BodyColliders;
+
+/// How far a body stopped by [continuous_collision] is left inside whatever it hit, so the narrow phase notices it.
+pub const CCD_SKIN: f32 = 0.01;
+
+/// Stops fast bodies marked with [Ccd] from tunnelling. Each part is swept from where the body started the step to where
+/// integration left it, and the body is pulled back to the first thing it would have hit so the narrow phase can deal
+/// with it. Whatever motion was left over is lost for this step.
+pub fn continuous_collision<B>(
+    broad: Res<B>,
+    colliders: BodyColliders,
+    mut bodies: Query<&mut RigidBody>,
+    fast: Query<(Entity, &PreviousRigidBody, Option<&BodyType>), (With<Ccd>, Without<Sensor>)>,
+    sensors: Query<(), With<Sensor>>,
+) where
+    B: BroadPhase,
+{
+    let mut clamped = Vec::new();
+    for (entity, previous, body_type) in fast.iter() {
+        if !body_type.copied().unwrap_or_default().is_dynamic() {
+            continue;
+        }
+        let rb = match bodies.get(entity) {
+            Ok(rb) => *rb,
+            Err(_) => continue,
+        };
+        let translation = rb.position - previous.0.position;
+        let distance = translation.length();
+
+        let mut first: Option<f32> = None;
+        for part in colliders.parts(entity) {
+            // Swept at its final rotation, starting from where the body was.
+            let (position, rotation) = part.placement(&rb);
+            let start = position - translation;
+            let start_shape = part.shape.wrap(Point::new(start.x, start.y), rotation);
+            let bounds = start_shape.bounding_rect();
+
+            // Moving less than half its own size, where the part started and where it ended overlap enough to cover all
+            // but a sliver along the sides of the sweep. Anything in the way is touching one or the other, so the narrow
+            // phase already sees it.
+            let size = (bounds.max.x - bounds.min.x).min(bounds.max.y - bounds.min.y);
+            if distance <= size / 2.0 {
+                continue;
+            }
+
+            // The broad phase still has everything else where it was last step, which is close enough.
+            let region = cast::swept_bounds(&bounds, translation);
+            for other in broad.query(&region, part.groups) {
+                if other == entity || sensors.get(other).is_ok() {
+                    continue;
+                }
+                let other_rb = match bodies.get(other) {
+                    Ok(rb) => *rb,
+                    Err(_) => continue,
+                };
+                for target in colliders.parts(other) {
+                    if !part.groups.interacts_with(&target.groups) {
+                        continue;
+                    }
+                    // Already touching at the start is something the narrow phase can handle on its own. Only this
+                    // collider is skipped, so anything further along the sweep still stops the body.
+                    let target_shape = target.wrap(&other_rb);
+                    if start_shape.contact(&target_shape).is_some() {
+                        continue;
+                    }
+                    match cast::cast_shape(&part.shape, start, rotation, translation, &target_shape) {
+                        Some((toi, _)) if first.map_or(true, |first| toi < first) => first = Some(toi),
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        if let Some(toi) = first {
+            let toi = (toi + CCD_SKIN / distance).min(1.0);
+            clamped.push((entity, previous.0.position + translation * toi));
+        }
+    }
+
+    for (entity, position) in clamped {
+        if let Ok(mut rb) = bodies.get_mut(entity) {
+            rb.position = position;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{broad_phase::SweepAndPrune, components::Collider};
+
+    fn wall(world: &mut World, x: f32, width: f32) {
+        let rb = RigidBody::new(Point::new(x, 0.0));
+        let collider = Collider::rect(width, 4.0);
+        let (bounds, groups) = (collider.global_aabr(&rb), collider.groups);
+        let entity = world.spawn().insert_bundle((rb, collider, BodyType::Static)).id();
+        world.resource_mut::<SweepAndPrune>().insert(entity, bounds, groups);
+    }
+
+    #[test]
+    fn fast_bodies_stop_at_thin_walls() {
+        let mut world = World::new();
+        world.insert_resource(SweepAndPrune::new());
+        // Touching the bullet where it starts, which the narrow phase deals with.
+        wall(&mut world, 0.3, 0.2);
+        wall(&mut world, 5.0, 0.1);
+        let bullet = world
+            .spawn()
+            .insert_bundle((
+                RigidBody::new(Point::new(10.0, 0.0)),
+                PreviousRigidBody(RigidBody::new(Point::new(0.0, 0.0))),
+                Collider::circle(0.25),
+                Ccd,
+            ))
+            .id();
+
+        SystemStage::single(continuous_collision::<SweepAndPrune>).run(&mut world);
+        let x = world.get::<RigidBody>(bullet).unwrap().position.x;
+        assert!((x - (4.95 - 0.25 + CCD_SKIN)).abs() < 1e-4, "stopped at {}", x);
+
+        // A slow bullet is left to the narrow phase.
+        world.entity_mut(bullet).insert_bundle((
+            RigidBody::new(Point::new(4.5, 0.0)),
+            PreviousRigidBody(RigidBody::new(Point::new(4.3, 0.0))),
+        ));
+        SystemStage::single(continuous_collision::<SweepAndPrune>).run(&mut world);
+        assert_eq!(world.get::<RigidBody>(bullet).unwrap().position.x, 4.5);
+    }
+}
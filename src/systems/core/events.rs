@@ -0,0 +1,158 @@
+use bevy::{prelude::*, utils::HashSet};
+
+use crate::{
+    events::*,
+    pipeline::{ContactPairs, Manifolds, SensorOverlaps, TriggerPairs},
+};
+
+/// Compares this step's manifolds with the last, and lets everyone know which contacts have started or ended.
+/// Entities that lose their collider or body drop out of the manifolds, so they get an end event too.
+pub fn collision_events(
+    mut contacts: ResMut<ContactPairs>,
+    manifolds: Res<Manifolds>,
+    mut started: EventWriter<CollisionStarted>,
+    mut ended: EventWriter<CollisionEnded>,
+) {
+    let current: HashSet<(Entity, Entity)> = manifolds.iter().map(|m| ContactPairs::key(m.a, m.b)).collect();
+    let (new, old) = contacts.update(current);
+    for (a, b) in new {
+        started.send(CollisionStarted(a, b));
+    }
+    for (a, b) in old {
+        ended.send(CollisionEnded(a, b));
+    }
+}
+
+/// Compares this step's sensor overlaps with the last, sending enter, stay and exit events.
+pub fn trigger_events(
+    mut triggers: ResMut<TriggerPairs>,
+    overlaps: Res<SensorOverlaps>,
+    mut entered: EventWriter<TriggerEntered>,
+    mut stay: EventWriter<TriggerStay>,
+    mut exited: EventWriter<TriggerExited>,
+) {
+    let current: HashSet<(Entity, Entity)> = overlaps.pairs.iter().copied().collect();
+    let (new, old) = triggers.0.update(current);
+    for (sensor, other) in triggers.0.iter() {
+        if !new.contains(&(*sensor, *other)) {
+            stay.send(TriggerStay(*sensor, *other));
+        }
+    }
+    for (sensor, other) in new {
+        entered.send(TriggerEntered(sensor, other));
+    }
+    for (sensor, other) in old {
+        exited.send(TriggerExited(sensor, other));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::event::{Events, ManualEventReader};
+    use prima::prelude::*;
+
+    use super::*;
+    use crate::{components::Sensor, pipeline::{ContactPoint, Manifold}};
+
+    fn touching(a: Entity, b: Entity) -> Manifold {
+        Manifold {
+            a,
+            b,
+            n: Vector::new(1.0, 0.0),
+            f: None,
+            points: vec![ContactPoint {
+                c: Point::new(0.0, 0.0),
+                p: 0.1,
+                id: 0,
+                jn: 0.0,
+                jt: 0.0,
+                bias: 0.0,
+            }],
+            part_a: None,
+            part_b: None,
+        }
+    }
+
+    fn world() -> World {
+        let mut world = World::new();
+        world.insert_resource(ContactPairs::new());
+        world.insert_resource(Manifolds::new());
+        world.insert_resource(TriggerPairs::default());
+        world.insert_resource(SensorOverlaps::default());
+        world.insert_resource(Events::<CollisionStarted>::default());
+        world.insert_resource(Events::<CollisionEnded>::default());
+        world.insert_resource(Events::<TriggerEntered>::default());
+        world.insert_resource(Events::<TriggerStay>::default());
+        world.insert_resource(Events::<TriggerExited>::default());
+        world
+    }
+
+    /// Takes every event of the type sent since the last call.
+    fn drain<E: Copy + Send + Sync + 'static>(world: &mut World) -> Vec<E> {
+        let events = world.resource::<Events<E>>();
+        let sent = ManualEventReader::default().iter(events).copied().collect();
+        world.resource_mut::<Events<E>>().clear();
+        sent
+    }
+
+    /// Runs the system over the given contacts, returning the pairs that started and ended.
+    fn step(world: &mut World, manifolds: Vec<Manifold>) -> (Vec<CollisionStarted>, Vec<CollisionEnded>) {
+        world.resource_mut::<Manifolds>().set(manifolds);
+        SystemStage::single(collision_events).run(world);
+        world.clear_trackers();
+        (drain(world), drain(world))
+    }
+
+    /// Runs the system over the given sensor overlaps, returning the enter, stay and exit events in that order.
+    fn overlap(
+        world: &mut World,
+        pairs: Vec<(Entity, Entity)>,
+    ) -> (Vec<TriggerEntered>, Vec<TriggerStay>, Vec<TriggerExited>) {
+        world.resource_mut::<SensorOverlaps>().pairs = pairs;
+        SystemStage::single(trigger_events).run(world);
+        world.clear_trackers();
+        (drain(world), drain(world), drain(world))
+    }
+
+    #[test]
+    fn contacts_start_once_and_end_once() {
+        let mut world = world();
+        let a = world.spawn().id();
+        let b = world.spawn().id();
+        let c = world.spawn().id();
+        let (ab, ac) = (ContactPairs::key(a, b), ContactPairs::key(a, c));
+
+        assert_eq!(step(&mut world, vec![touching(a, b)]), (vec![CollisionStarted(ab.0, ab.1)], vec![]));
+        // Still touching, and found the other way round this time.
+        assert_eq!(step(&mut world, vec![touching(b, a)]), (vec![], vec![]));
+        assert_eq!(step(&mut world, vec![]), (vec![], vec![CollisionEnded(ab.0, ab.1)]));
+
+        assert_eq!(step(&mut world, vec![touching(a, c)]), (vec![CollisionStarted(ac.0, ac.1)], vec![]));
+        // Losing the collider drops the pair from the manifolds, which ends the contact once.
+        world.entity_mut(c).despawn();
+        assert_eq!(step(&mut world, vec![]), (vec![], vec![CollisionEnded(ac.0, ac.1)]));
+    }
+
+    #[test]
+    fn triggers_enter_stay_and_exit() {
+        let mut world = world();
+        let sensor = world.spawn().insert(Sensor).id();
+        let a = world.spawn().id();
+        let b = world.spawn().id();
+
+        assert_eq!(overlap(&mut world, vec![(sensor, a)]), (vec![TriggerEntered(sensor, a)], vec![], vec![]));
+        assert_eq!(overlap(&mut world, vec![(sensor, a)]), (vec![], vec![TriggerStay(sensor, a)], vec![]));
+        assert_eq!(
+            overlap(&mut world, vec![(sensor, a), (sensor, b)]),
+            (vec![TriggerEntered(sensor, b)], vec![TriggerStay(sensor, a)], vec![])
+        );
+        assert_eq!(
+            overlap(&mut world, vec![(sensor, b)]),
+            (vec![], vec![TriggerStay(sensor, b)], vec![TriggerExited(sensor, a)])
+        );
+
+        // No longer being a sensor drops it from the overlaps, which ends them once.
+        world.entity_mut(sensor).remove::<Sensor>();
+        assert_eq!(overlap(&mut world, vec![]), (vec![], vec![], vec![TriggerExited(sensor, b)]));
+    }
+}
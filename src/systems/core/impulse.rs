@@ -2,8 +2,8 @@ use bevy::prelude::*;
 use prima::prelude::*;
 
 use crate::{
-    components::{Mass, RigidBody, Velocity, PhysicsMaterial},
-    pipeline::{generate_impulse_pair, Manifolds},
+    components::{BodyType, CenterOfMass, Inertia, Mass, RigidBody, Sleep, Velocity, PhysicsMaterial},
+    pipeline::{generate_impulse_pair, ImpulseResult, Manifolds},
 };
 
 use super::ImpulseResolver;
@@ -12,36 +12,93 @@ pub fn impulse_resolution<F>(
     mut resolver: ResMut<F>,
     mut vq: Query<&mut Velocity>,
     mut rbq: Query<&mut RigidBody>,
-    manifolds: Res<Manifolds>,
+    mut manifolds: ResMut<Manifolds>,
     materials: Res<Assets<PhysicsMaterial>>,
     mq: Query<&Mass>,
+    iq: Query<&Inertia>,
+    cq: Query<&CenterOfMass>,
+    tq: Query<&BodyType>,
+    sq: Query<&Sleep>,
     mat_handles: Query<&Handle<PhysicsMaterial>>,
 ) where
     F: ImpulseResolver,
 {
     resolver.tick();
-    
-    for manifold in manifolds.iter() {
+
+    // Only dynamic bodies are ever pushed, so contacts without one have nothing to resolve. Sleeping bodies hold on to
+    // their contacts, but nothing is resolved until something awake or a moving kinematic body touches them.
+    let dynamic = |entity: Entity| tq.get(entity).copied().unwrap_or_default().is_dynamic();
+    let moving = |entity: Entity| vq.get(entity).map_or(false, |v| v.magnitide_squared() > 0.0 || v.angular() != 0.0);
+    let awake = |entity: Entity| match tq.get(entity).copied().unwrap_or_default() {
+        BodyType::Dynamic => !sq.get(entity).map_or(false, |s| s.is_asleep()),
+        body_type => body_type.is_kinematic() && moving(entity),
+    };
+    // Worked out up front, as resolving the contacts changes velocities.
+    let active: Vec<bool> = manifolds.iter().map(|m| awake(m.a) || awake(m.b)).collect();
+
+    for (manifold, active) in manifolds.iter_mut().zip(active.iter()) {
+        if !active {
+            continue;
+        }
         // Collect impulse data.
-        let (a, b) = generate_impulse_pair(&mut vq, &mut rbq, manifold, &mq, &materials, &mat_handles);
+        let (a, b) = match generate_impulse_pair(&mut vq, &mut rbq, manifold, &mq, &iq, &cq, &tq, &materials, &mat_handles) {
+            Some(pair) => pair,
+            None => continue,
+        };
 
         // Calculate the initial force of the collision.
-        let initial_force = a.m * a.v.magnitude() + b.m * b.v.magnitude();
-
-        // Send impulse data to the collision resolution function.
-        let (a, b) = resolver.resolve(manifold.with_initial_force(initial_force), a, b);
-
-        // Apply impulses!
-        let mut va = vq.get_mut(manifold.a).unwrap();
-        va.set_linear(a.v);
-        va.set_angular(a.r);
-        let mut vb = vq.get_mut(manifold.b).unwrap();
-        vb.set_linear(b.v);
-        vb.set_angular(b.r);
-
-        let mut rba = rbq.get_mut(manifold.a).unwrap();
-        rba.position += Vec2::new(a.t.x, a.t.y);
-        let mut rbb = rbq.get_mut(manifold.b).unwrap();
-        rbb.position += Vec2::new(b.t.x, b.t.y);
+        manifold.f = Some(a.m * a.v.magnitude() + b.m * b.v.magnitude());
+
+        let (ra, rb) = resolver.prepare(manifold, a, b);
+        if dynamic(manifold.a) {
+            apply_result(manifold.a, ra, false, &mut vq, &mut rbq);
+        }
+        if dynamic(manifold.b) {
+            apply_result(manifold.b, rb, false, &mut vq, &mut rbq);
+        }
+    }
+
+    let iterations = resolver.iterations().max(1);
+    for iteration in 0..iterations {
+        // Positional correction is only taken from the last pass, so it does not stack up.
+        let translate = iteration + 1 == iterations;
+        for (manifold, active) in manifolds.iter_mut().zip(active.iter()) {
+            if !active {
+                continue;
+            }
+            let (a, b) = match generate_impulse_pair(&mut vq, &mut rbq, manifold, &mq, &iq, &cq, &tq, &materials, &mat_handles) {
+                Some(pair) => pair,
+                None => continue,
+            };
+
+            // Send impulse data to the collision resolution function.
+            let (ra, rb) = resolver.resolve(manifold, a, b);
+
+            // Apply impulses! Kinematic and static bodies push, but are never pushed back.
+            if dynamic(manifold.a) {
+                apply_result(manifold.a, ra, translate, &mut vq, &mut rbq);
+            }
+            if dynamic(manifold.b) {
+                apply_result(manifold.b, rb, translate, &mut vq, &mut rbq);
+            }
+        }
+    }
+}
+
+fn apply_result(
+    entity: Entity,
+    result: ImpulseResult,
+    translate: bool,
+    vq: &mut Query<&mut Velocity>,
+    rbq: &mut Query<&mut RigidBody>,
+) {
+    if let Ok(mut velocity) = vq.get_mut(entity) {
+        velocity.set_linear(result.v);
+        velocity.set_angular(result.r);
+    }
+    if translate {
+        if let Ok(mut rb) = rbq.get_mut(entity) {
+            rb.position += Vec2::new(result.t.x, result.t.y);
+        }
     }
 }
@@ -1,25 +1,91 @@
 use bevy::prelude::*;
 use prima::prelude::*;
 
-use crate::{components::{Forces, Mass, RigidBody, Velocity}, resources::FishicsConfig};
+use crate::{components::{BodyType, CenterOfMass, Forces, GravityScale, Mass, RigidBody, Sleep, Velocity}, resources::FishicsConfig, shapes::turn};
 
 /// Apply pending forces and rotations, as well as normalize any skewed values.
 pub fn integration(
-    time: Res<Time>,
     cfg: Res<FishicsConfig>,
-    mut bodies: Query<(&mut RigidBody, &mut Forces, &mut Velocity, &Mass)>,
+    mut bodies: Query<(
+        &mut RigidBody,
+        &mut Velocity,
+        Option<&mut Forces>,
+        Option<&Mass>,
+        Option<&GravityScale>,
+        Option<&CenterOfMass>,
+        Option<&BodyType>,
+        Option<&Sleep>,
+    )>,
 ) {
-    let dt = time.delta_seconds() * cfg.time;
-    for (mut rb, mut force, mut velocity, mass) in bodies.iter_mut() {
-        if mass.raw() == 0.0 {
+    let dt = cfg.dt();
+    let gravity = Vector::new(cfg.gravity.x, cfg.gravity.y);
+    for (mut rb, mut velocity, force, mass, gravity_scale, center_of_mass, body_type, sleep) in bodies.iter_mut() {
+        // Sleeping bodies stay exactly where they are, forces and all, until they are woken.
+        if sleep.map_or(false, |s| s.is_asleep()) {
             continue;
         }
-        // Symplectic Euler integration. The order of the next two lines is important!
-        velocity.add_linear(force.collect_impulse() * mass.inv() * dt);
+        match body_type.copied().unwrap_or_default() {
+            // Static bodies never move, and position driven ones are moved by hand.
+            BodyType::Static | BodyType::KinematicPosition => continue,
+            // Velocity driven bodies just keep going, whatever else is happening.
+            BodyType::KinematicVelocity => {}
+            BodyType::Dynamic => {
+                let mass = match mass {
+                    Some(mass) if mass.raw() != 0.0 => mass,
+                    _ => continue,
+                };
+                // Gravity is an acceleration, so it is applied regardless of mass.
+                let scale = gravity_scale.map(|g| g.raw()).unwrap_or(1.0);
+                velocity.add_linear(gravity * scale * dt);
+
+                // Symplectic Euler integration. Velocity has to be updated before the body is moved!
+                if let Some(mut force) = force {
+                    velocity.add_linear(force.collect_impulse() * mass.inv() * dt);
+                }
+            }
+        }
         rb.translate(velocity.linear() * dt);
 
-        // Normalize the rotation
-        let r = rb.rotation + velocity.angular() * dt;
-        rb.rotation = Rotation::from_radians(r).as_radians();
+        // Angular velocity is in radians, the stored rotation is not. The body turns about its center of mass, so the
+        // position swings round it when the two are apart.
+        let offset = center_of_mass.map(|c| c.raw()).unwrap_or(Vec2::ZERO);
+        let pivot = rb.position + turn(offset, rb.applied_rotation());
+        rb.rotate(velocity.angular() * dt);
+        rb.position = pivot - turn(offset, rb.applied_rotation());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gravity_is_scaled_per_body() {
+        let mut world = World::new();
+        world.insert_resource(FishicsConfig::default().with_gravity(Vec2::new(0.0, -10.0)));
+
+        let mut spawn = |scale: f32| {
+            world
+                .spawn()
+                .insert_bundle((
+                    RigidBody::new(Point::new(0.0, 0.0)),
+                    Forces::default(),
+                    Velocity::default(),
+                    Mass::new(1.0),
+                    GravityScale::new(scale),
+                ))
+                .id()
+        };
+        let normal = spawn(1.0);
+        let weightless = spawn(0.0);
+        let inverted = spawn(-2.0);
+
+        SystemStage::single(integration).run(&mut world);
+
+        // One step of the default 1/60s timestep.
+        let fall = world.get::<Velocity>(normal).unwrap().linear();
+        assert!((fall.y + 10.0 / 60.0).abs() < 1e-6);
+        assert_eq!(world.get::<Velocity>(weightless).unwrap().magnitude(), 0.0);
+        assert!((world.get::<Velocity>(inverted).unwrap().linear().y + 2.0 * fall.y).abs() < 1e-6);
     }
 }
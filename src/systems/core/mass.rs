@@ -0,0 +1,128 @@
+use bevy::prelude::*;
+
+use crate::components::{CenterOfMass, Inertia, Mass, PhysicsMaterial, RigidBody, DEFAULT_DENSITY};
+
+use super::BodyColliders;
+
+/// Works out automatic masses, inertias and centers of mass from the shapes of each body's colliders and its material
+/// density.
+pub fn mass_properties(
+    materials: Res<Assets<PhysicsMaterial>>,
+    colliders: BodyColliders,
+    mut bodies: Query<
+        (
+            Entity,
+            Option<&Handle<PhysicsMaterial>>,
+            Option<&mut Mass>,
+            Option<&mut Inertia>,
+            Option<&mut CenterOfMass>,
+        ),
+        With<RigidBody>,
+    >,
+) {
+    for (entity, handle, mass, inertia, center_of_mass) in bodies.iter_mut() {
+        let parts = colliders.parts(entity);
+        if parts.is_empty() {
+            continue;
+        }
+        let density = handle
+            .and_then(|handle| materials.get(handle))
+            .map(|material| material.density)
+            .unwrap_or(DEFAULT_DENSITY);
+
+        // Only write when something has changed, so change detection stays quiet.
+        let areas: Vec<f32> = parts.iter().map(|part| part.shape.area()).collect();
+        let area: f32 = areas.iter().sum();
+        let mut m = area * density;
+        if let Some(mut mass) = mass {
+            if !mass.is_auto() {
+                m = mass.raw();
+            } else if mass.raw() != m {
+                mass.set_auto(m);
+            }
+        }
+
+        // The mass is shared out by area, so an explicit mass still spreads across every part.
+        let part_masses: Vec<f32> = areas
+            .iter()
+            .map(|a| if area > 0.0 { m * a / area } else { 0.0 })
+            .collect();
+        let center = if m > 0.0 {
+            parts
+                .iter()
+                .zip(part_masses.iter())
+                .fold(Vec2::ZERO, |center, (part, pm)| center + part.center() * *pm)
+                / m
+        } else {
+            Vec2::ZERO
+        };
+        if let Some(mut center_of_mass) = center_of_mass {
+            if center_of_mass.raw() != center {
+                center_of_mass.set(center);
+            }
+        }
+
+        if let Some(mut inertia) = inertia {
+            // Each part spins about its own center, moved out to the body's center of mass.
+            let i: f32 = parts
+                .iter()
+                .zip(part_masses.iter())
+                .map(|(part, pm)| part.shape.inertia(*pm) + pm * (part.center() - center).length_squared())
+                .sum();
+            if inertia.is_auto() && inertia.raw() != i {
+                inertia.set_auto(i);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use bevy::{asset::AssetPlugin, core::CorePlugin};
+
+    use super::*;
+    use crate::components::Collider;
+
+    #[test]
+    fn mass_and_inertia_follow_the_shape() {
+        let mut app = App::new();
+        app.add_plugin(CorePlugin)
+            .add_plugin(AssetPlugin)
+            .add_asset::<PhysicsMaterial>()
+            .add_system(mass_properties);
+        let dense = app
+            .world
+            .resource_mut::<Assets<PhysicsMaterial>>()
+            .add(PhysicsMaterial::default().with_density(2.0));
+
+        let rect = app
+            .world
+            .spawn()
+            .insert_bundle((RigidBody::default(), Collider::rect(2.0, 3.0), Mass::auto(), Inertia::auto()))
+            .id();
+        let circle = app
+            .world
+            .spawn()
+            .insert_bundle((RigidBody::default(), Collider::circle(1.0), dense, Mass::auto(), Inertia::auto()))
+            .id();
+        // A mass set by hand is kept, but still feeds into the automatic inertia.
+        let heavy = app
+            .world
+            .spawn()
+            .insert_bundle((RigidBody::default(), Collider::rect(2.0, 3.0), Mass::new(12.0), Inertia::auto()))
+            .id();
+        app.update();
+
+        let properties = |entity| {
+            let mass = app.world.get::<Mass>(entity).unwrap();
+            let inertia = app.world.get::<Inertia>(entity).unwrap();
+            (mass.raw(), inertia.raw())
+        };
+        assert_eq!(properties(rect), (6.0, 6.5));
+        assert_eq!(properties(circle), (2.0 * PI, PI));
+        assert_eq!(properties(heavy), (12.0, 13.0));
+        assert!(!app.world.get::<Mass>(heavy).unwrap().is_auto());
+    }
+}
@@ -1,11 +1,23 @@
 mod broad;
+mod ccd;
+mod events;
 mod impulse;
 mod intergration;
+mod mass;
 mod narrow;
+mod parts;
 mod resolution;
+mod sequential;
+mod sleep;
 
 pub use broad::*;
+pub use ccd::*;
+pub use events::*;
 pub use impulse::*;
 pub use intergration::*;
+pub use mass::*;
 pub use narrow::*;
-pub use resolution::*;
\ No newline at end of file
+pub use parts::*;
+pub use resolution::*;
+pub use sequential::*;
+pub use sleep::*;
\ No newline at end of file
@@ -1,30 +1,75 @@
 use bevy::prelude::*;
 use prima::prelude::*;
 
-use crate::{pipeline::{Manifolds, BroadPhasePairs, Manifold}, components::{RigidBody, Collider}};
+use crate::{pipeline::{Manifolds, BroadPhasePairs, Manifold, SensorOverlaps, ContactPairs, TriggerPairs}, components::{BodyType, RigidBody, Sensor, Sleep}};
+
+use super::{is_idle, BodyColliders};
 
 /// Find actual collisions.
 pub fn narrow_phase(
     mut manifolds: ResMut<Manifolds>,
+    mut overlaps: ResMut<SensorOverlaps>,
     pairs: Res<BroadPhasePairs>,
     bodies: Query<&RigidBody>,
-    colliders: Query<&Collider>,
+    colliders: BodyColliders,
+    sensors: Query<(), With<Sensor>>,
+    triggers: Res<TriggerPairs>,
+    types: Query<&BodyType>,
+    sleeping: Query<&Sleep>,
 ) {
     let mut new_manifolds = Vec::new();
+    let mut new_overlaps = Vec::new();
 
     for (a, b) in pairs.pairs.iter() {
-        let a_rb = bodies.get(*a).unwrap();
-        let b_rb = bodies.get(*b).unwrap();
-        let a_col = colliders.get(*a).unwrap();
-        let b_col = colliders.get(*b).unwrap();
+        // The broad phase hears about removals a frame late, so anything missing is skipped.
+        let (a_rb, b_rb) = match (bodies.get(*a), bodies.get(*b)) {
+            (Ok(a_rb), Ok(b_rb)) => (a_rb, b_rb),
+            _ => continue,
+        };
+        let a_parts = colliders.parts(*a);
+        let b_parts = colliders.parts(*b);
+
+        // Sensors only care about overlap, and never make it into the manifolds.
+        let a_sensor = sensors.get(*a).is_ok();
+        let b_sensor = sensors.get(*b).is_ok();
+
+        // Every part of one body is tested against every part of the other, as long as their groups agree.
+        let mut overlapping = false;
+        for a_part in a_parts.iter() {
+            let a_shape = a_part.wrap(a_rb);
+            for b_part in b_parts.iter() {
+                if !a_part.groups.interacts_with(&b_part.groups) {
+                    continue;
+                }
+                let b_shape = b_part.wrap(b_rb);
 
-        let a_shape = a_col.shape.wrap(a_rb.position());
-        let b_shape = b_col.shape.wrap(b_rb.position());
+                if a_sensor || b_sensor {
+                    overlapping |= a_shape.collision(&b_shape).is_some()
+                        || a_shape.enveloping(&b_shape)
+                        || a_shape.enveloped_by(&b_shape);
+                    continue;
+                }
 
-        if let Some(collision) = a_shape.collision(&b_shape) {
-            //println!("{:?}", collision);
-            new_manifolds.push(Manifold::new(*a, *b, collision));
+                if let Some(contact) = a_shape.contact(&b_shape) {
+                    new_manifolds.push(Manifold::new(*a, *b, contact).with_parts(a_part.id, b_part.id));
+                }
+            }
+        }
+
+        if overlapping {
+            new_overlaps.push(match (a_sensor, b_sensor) {
+                (true, false) => (*a, *b),
+                (false, true) => (*b, *a),
+                _ => ContactPairs::key(*a, *b),
+            });
         }
     }
-    manifolds.set(new_manifolds);
+    // The broad phase leaves out pairs that are both static or asleep, but they are still touching. They keep the contacts
+    // and overlaps they had until one of them wakes, so nothing looks like it has ended in the meantime.
+    let idle = |entity: Entity| bodies.get(entity).is_ok() && is_idle(entity, &types, &sleeping);
+    new_manifolds.extend(manifolds.iter().filter(|m| idle(m.a) && idle(m.b)).cloned());
+    new_overlaps.extend(triggers.0.iter().filter(|(sensor, other)| idle(*sensor) && idle(*other)).copied());
+
+    manifolds.update(new_manifolds);
+    overlaps.pairs = new_overlaps;
 }
@@ -0,0 +1,108 @@
+use bevy::{ecs::system::SystemParam, prelude::*};
+use prima::prelude::*;
+
+use crate::{
+    components::{Collider, ColliderOffset, CollisionGroups, CompoundCollider, PartId, RigidBody},
+    shapes::turn,
+    AbstractShape, ShapeWrapper,
+};
+
+/// One shape of a body, wherever it came from.
+#[derive(Debug, Clone)]
+pub struct BodyPart {
+    /// None for the body's own [Collider].
+    pub id: Option<PartId>,
+    pub shape: AbstractShape,
+    pub offset: Vec2,
+    pub rotation: f32,
+    pub groups: CollisionGroups,
+}
+
+/// Gathers every shape that makes up a body: its own [Collider], the parts of its [CompoundCollider], and any children
+/// with a [Collider] of their own.
+#[derive(SystemParam)]
+pub struct BodyColliders<'w, 's> {
+    colliders: Query<'w, 's, &'static Collider, With<RigidBody>>,
+    compounds: Query<'w, 's, &'static CompoundCollider, With<RigidBody>>,
+    children: Query<'w, 's, &'static Children, With<RigidBody>>,
+    child_colliders: Query<'w, 's, (&'static Collider, Option<&'static ColliderOffset>), Without<RigidBody>>,
+}
+
+impl BodyPart {
+    /// Places the part in the world, on a body.
+    pub fn wrap(&self, rb: &RigidBody) -> ShapeWrapper {
+        let (position, rotation) = self.placement(rb);
+        self.shape.wrap(Point::new(position.x, position.y), rotation)
+    }
+
+    /// Where the part sits in the world on a body, and its rotation in radians.
+    pub fn placement(&self, rb: &RigidBody) -> (Vec2, f32) {
+        let rotation = rb.applied_rotation();
+        (rb.position + turn(self.offset, rotation), rotation + self.rotation)
+    }
+
+    /// Where the part's shape is centered, relative to its body.
+    pub fn center(&self) -> Vec2 {
+        self.offset + turn(self.shape.centroid(), self.rotation)
+    }
+}
+
+impl<'w, 's> BodyColliders<'w, 's> {
+    pub fn parts(&self, entity: Entity) -> Vec<BodyPart> {
+        let mut parts = Vec::new();
+        if let Ok(collider) = self.colliders.get(entity) {
+            parts.push(BodyPart {
+                id: None,
+                shape: collider.shape.clone(),
+                offset: Vec2::ZERO,
+                rotation: 0.0,
+                groups: collider.groups,
+            });
+        }
+        if let Ok(compound) = self.compounds.get(entity) {
+            for (i, part) in compound.parts.iter().enumerate() {
+                parts.push(BodyPart {
+                    id: Some(PartId::Index(i)),
+                    shape: part.shape.clone(),
+                    offset: part.offset,
+                    rotation: part.rotation,
+                    groups: compound.groups,
+                });
+            }
+        }
+        if let Ok(children) = self.children.get(entity) {
+            for child in children.iter() {
+                if let Ok((collider, offset)) = self.child_colliders.get(*child) {
+                    let offset = offset.copied().unwrap_or_default();
+                    parts.push(BodyPart {
+                        id: Some(PartId::Child(*child)),
+                        shape: collider.shape.clone(),
+                        offset: offset.translation,
+                        rotation: offset.rotation,
+                        groups: collider.groups,
+                    });
+                }
+            }
+        }
+        parts
+    }
+
+    /// Bounds around every part of the body, along with every group any part is in or accepts. Returns None if the
+    /// body has no parts at all.
+    pub fn bounds(&self, entity: Entity, rb: &RigidBody) -> Option<(Aabr<f32>, CollisionGroups)> {
+        let parts = self.parts(entity);
+        let mut parts = parts.iter();
+        let first = parts.next()?;
+        let bounds = first.wrap(rb).bounding_rect();
+        let (mut min, mut max) = (bounds.min, bounds.max);
+        let mut groups = first.groups;
+        for part in parts {
+            let bounds = part.wrap(rb).bounding_rect();
+            min = Point::new(min.x.min(bounds.min.x), min.y.min(bounds.min.y));
+            max = Point::new(max.x.max(bounds.max.x), max.y.max(bounds.max.y));
+            groups.memberships |= part.groups.memberships;
+            groups.filter |= part.groups.filter;
+        }
+        Some((Aabr::new(min, max), groups))
+    }
+}
@@ -1,15 +1,26 @@
 use bevy::ecs::system::Resource;
 use bevy_inspector_egui::Inspectable;
+use prima::prelude::*;
 
-use crate::pipeline::{ImpulseObject, ImpulseResult, Manifold};
+use crate::{components::CombineRule, pipeline::{ImpulseObject, ImpulseResult, Manifold}};
 
 /// A trait that allows the struct to be used to solve collisions. While the classic 'resolver' supplied in the crate does not
 /// make any use of 'self', it is there if you want to use your own custom collision resolution algorithm.
 pub trait ImpulseResolver: Default + Resource {
     /// Tells the resolver a new round of collision resolution is starting.
     fn tick(&mut self);
-    /// Resolves the collision between two bodies.
-    fn resolve(&mut self, manifold: Manifold, a: ImpulseObject, b: ImpulseObject) -> (ImpulseResult, ImpulseResult);
+    /// How many times every manifold is passed to [ImpulseResolver::resolve] each step. Positional corrections are only
+    /// taken from the last pass.
+    fn iterations(&self) -> u32 {
+        1
+    }
+    /// Called once for each manifold before any resolution happens, so per-contact state can be set up.
+    fn prepare(&mut self, _manifold: &mut Manifold, a: ImpulseObject, b: ImpulseObject) -> (ImpulseResult, ImpulseResult) {
+        (a.into(), b.into())
+    }
+    /// Resolves the collision between two bodies. Every point in the manifold shares its normal, so they should all be
+    /// handled in the one call.
+    fn resolve(&mut self, manifold: &mut Manifold, a: ImpulseObject, b: ImpulseObject) -> (ImpulseResult, ImpulseResult);
 }
 
 /// Calculates the resulting velocities of two objects after a collision.
@@ -21,6 +32,13 @@ pub struct ClassicImpulseResolver {
     pub correction: f32,
     #[inspectable(min = 0.0, max = 0.1)]
     pub slop: f32,
+    /// Used to merge restitution coefficients, unless one of the materials asks otherwise.
+    pub restitution_combine: CombineRule,
+    /// Contacts closing slower than this do not bounce at all, which stops resting stacks from jittering.
+    #[inspectable(min = 0.0)]
+    pub restitution_threshold: f32,
+    /// Used to merge friction coefficients, unless one of the materials asks otherwise.
+    pub friction_combine: CombineRule,
     #[inspectable(read_only)]
     ticks: u32,
     #[inspectable(read_only)]
@@ -32,6 +50,9 @@ impl Default for ClassicImpulseResolver {
         Self {
             correction: 0.95,
             slop: 0.0,
+            restitution_combine: CombineRule::Average,
+            restitution_threshold: 1.0,
+            friction_combine: CombineRule::Average,
             ticks: 0,
             collisions: 0,
         }
@@ -49,53 +70,223 @@ impl ImpulseResolver for ClassicImpulseResolver {
 
     fn resolve(
         &mut self,
-        m: Manifold,
+        m: &mut Manifold,
         a: ImpulseObject,
         b: ImpulseObject,
     ) -> (ImpulseResult, ImpulseResult) {
         let mut r1: ImpulseResult = a.into();
         let mut r2: ImpulseResult = b.into();
-    
-        if m.f() <= 0. {
-            return (r1, r2);
-        }
 
+        // Whether anything happens is down to each point's closing speed and the depth, not the force behind it. A
+        // kinematic body walking into a resting one has no force to speak of, but still has to push.
         self.collisions += 1;
-        
-        let rv_n = (a.v - b.v).dot(&m.n);
-        
-        // // Numerator
-        // let numerator = -(1. + a.cr) * rv_n;
 
-        // // Denominator
-        // let mass_sum_inv = a.i + b.i;
-        // let a_arm = m.c - a.c;
-        // let b_arm = m.c - b.c;
+        // The impulse is shared between the points, so a box resting on two corners gets the same push as one on a single point.
+        let share = 1.0 / m.points.len().max(1) as f32;
+        let n = m.n;
+
+        for point in m.points.iter() {
+            // Lever arms from each center of mass to the contact point.
+            let a_arm = Vector::new(point.c.x - a.c.x, point.c.y - a.c.y);
+            let b_arm = Vector::new(point.c.x - b.c.x, point.c.y - b.c.y);
+
+            // Relative velocity at the contact point, including any spin.
+            let a_vel = a.v + cross_scalar(a.r, a_arm);
+            let b_vel = b.v + cross_scalar(b.r, b_arm);
+            let rv_n = (a_vel - b_vel).dot(&n);
 
-        // let a_rap = a_arm.cross_product(&m.n).powi(2) * a.mi;
-        // let b_rap = b_arm.cross_product(&m.n).powi(2) * b.mi;
-        
-        // let denom = mass_sum_inv + a_rap + b_rap;
+            let a_rn = cross(a_arm, n);
+            let b_rn = cross(b_arm, n);
+            let denom = a.i + b.i + a_rn * a_rn * a.mi + b_rn * b_rn * b.mi;
 
-        // let j = numerator / denom;
+            // Only push the bodies apart if they are still moving towards each other.
+            if rv_n <= 0.0 || denom <= 0.0 {
+                continue;
+            }
 
-        // r1.v = a.v + m.n * j * a.i;
-        // r2.v = b.v - m.n * j * b.i;
+            let rule = CombineRule::pick(self.restitution_combine, a.rc, b.rc);
+            let e = if rv_n < self.restitution_threshold {
+                0.0
+            } else {
+                rule.combine(a.cr, b.cr)
+            };
+            let j = (-(1.0 + e) * rv_n) / denom * share;
+            let impulse = n * j;
 
-        // r1.r = a.r + a.mi * a_rap * j;
-        // r2.r = b.r - b.mi * b_rap * j;
+            r1.v = r1.v + (impulse * a.i);
+            r2.v = r2.v - (impulse * b.i);
+            r1.r += cross(a_arm, impulse) * a.mi;
+            r2.r -= cross(b_arm, impulse) * b.mi;
 
-        let e = 1.0;
-        let j = (-(1.0 + e) * rv_n) / (a.i + b.i);
-        r1.v = a.v + (m.n * a.i * j);
-        r2.v = b.v - (m.n * b.i * j);
+            // Friction works against whatever sliding is left after the bounce.
+            let a_vel = r1.v + cross_scalar(r1.r, a_arm);
+            let b_vel = r2.v + cross_scalar(r2.r, b_arm);
+            let rv = a_vel - b_vel;
+            let tangent = rv - n * rv.dot(&n);
+            if tangent.magnitude_squared() > f32::EPSILON {
+                let t = tangent.normalize();
+                let a_rt = cross(a_arm, t);
+                let b_rt = cross(b_arm, t);
+                let denom_t = a.i + b.i + a_rt * a_rt * a.mi + b_rt * b_rt * b.mi;
+
+                let rule = CombineRule::pick(self.friction_combine, a.fc, b.fc);
+                let static_friction = rule.combine(a.sf, b.sf);
+                let dynamic_friction = rule.combine(a.df, b.df);
+
+                // Coulomb's law: stick if the normal impulse can hold it, otherwise slide.
+                let jn = -j;
+                let mut jt = -rv.dot(&t) / denom_t * share;
+                if jt.abs() > jn * static_friction {
+                    jt = jt.signum() * jn * dynamic_friction;
+                }
+
+                let friction = t * jt;
+                r1.v = r1.v + (friction * a.i);
+                r2.v = r2.v - (friction * b.i);
+                r1.r += cross(a_arm, friction) * a.mi;
+                r2.r -= cross(b_arm, friction) * b.mi;
+            }
+        }
 
         // Positional correction
-        let correction = m.n * (m.p - self.slop).max(0.0) / (a.i + b.i) * self.correction;
-        r1.t = -correction * a.i;
-        r2.t = correction * b.i;
+        if a.i + b.i > 0.0 {
+            let correction = m.n * (m.depth() - self.slop).max(0.0) / (a.i + b.i) * self.correction;
+            r1.t = -correction * a.i;
+            r2.t = correction * b.i;
+        }
     
-        //println!("collision {}: {:#?}", self.collisions, m);
         (r1, r2)
     }
-}
\ No newline at end of file
+}
+
+/// The 2D cross product of two vectors.
+pub(crate) fn cross(a: Vector<f32>, b: Vector<f32>) -> f32 {
+    a.x * b.y - a.y * b.x
+}
+
+/// The cross product of an angular velocity and a vector, giving the linear velocity of that point.
+pub(crate) fn cross_scalar(w: f32, v: Vector<f32>) -> Vector<f32> {
+    Vector::new(-w * v.y, w * v.x)
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::Entity;
+
+    use super::*;
+    use crate::pipeline::ContactPoint;
+
+    fn body(v: Vector<f32>, i: f32, mi: f32) -> ImpulseObject {
+        ImpulseObject {
+            m: if i > 0.0 { 1.0 / i } else { 0.0 },
+            i,
+            v,
+            r: 0.0,
+            c: Point::new(0.0, 0.0),
+            mi,
+            cr: 1.0,
+            rc: None,
+            sf: 0.0,
+            df: 0.0,
+            fc: None,
+        }
+    }
+
+    fn contact(x: f32, y: f32) -> Manifold {
+        Manifold {
+            a: Entity::from_raw(0),
+            b: Entity::from_raw(1),
+            n: Vector::new(1.0, 0.0),
+            f: Some(1.0),
+            points: vec![ContactPoint {
+                c: Point::new(x, y),
+                p: 0.0,
+                id: 0,
+                jn: 0.0,
+                jt: 0.0,
+                bias: 0.0,
+            }],
+            part_a: None,
+            part_b: None,
+        }
+    }
+
+    #[test]
+    fn off_center_contacts_spin_the_body() {
+        let mut resolver = ClassicImpulseResolver::default();
+        let wall = body(Vector::zero(), 0.0, 0.0);
+
+        // Hit square on, the body bounces straight back.
+        let (a, _) = resolver.resolve(&mut contact(0.0, 0.0), body(Vector::new(1.0, 0.0), 1.0, 1.0), wall);
+        assert_eq!((a.v.x, a.v.y, a.r), (-1.0, 0.0, 0.0));
+
+        // Hit one unit above the center, half the impulse goes into turning it, anticlockwise as the top is pushed back.
+        let (a, _) = resolver.resolve(&mut contact(0.0, 1.0), body(Vector::new(1.0, 0.0), 1.0, 1.0), wall);
+        assert_eq!((a.v.x, a.v.y, a.r), (0.0, 0.0, 1.0));
+
+        // Without any inertia to turn, it bounces as if hit square on.
+        let (a, _) = resolver.resolve(&mut contact(0.0, 1.0), body(Vector::new(1.0, 0.0), 1.0, 0.0), wall);
+        assert_eq!((a.v.x, a.v.y, a.r), (-1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn separating_bodies_are_left_alone() {
+        let mut resolver = ClassicImpulseResolver::default();
+        let (a, b) = resolver.resolve(
+            &mut contact(0.0, 1.0),
+            body(Vector::new(-1.0, 0.0), 1.0, 1.0),
+            body(Vector::zero(), 1.0, 1.0),
+        );
+        assert_eq!((a.v.x, a.r, b.v.x, b.r), (-1.0, 0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn friction_follows_the_combine_rule() {
+        let mut resolver = ClassicImpulseResolver::default();
+        let mut wall = body(Vector::zero(), 0.0, 0.0);
+        // Slides down the wall as it hits it. The bounce gives a normal impulse of 2.
+        let mut a = body(Vector::new(1.0, 1.0), 1.0, 0.0);
+        a.sf = 0.5;
+        a.df = 0.5;
+
+        // Averaged with the frictionless wall, the friction can only take off 0.5 of the sliding.
+        let (r, _) = resolver.resolve(&mut contact(0.0, 0.0), a, wall);
+        assert_eq!((r.v.x, r.v.y), (-1.0, 0.5));
+
+        // The wall asks for the larger of the two, which is enough to stop the sliding dead.
+        wall.fc = Some(CombineRule::Max);
+        let (r, _) = resolver.resolve(&mut contact(0.0, 0.0), a, wall);
+        assert_eq!((r.v.x, r.v.y), (-1.0, 0.0));
+
+        // The resolver's own rule is only used when neither material has one.
+        wall.fc = None;
+        resolver.friction_combine = CombineRule::Min;
+        let (r, _) = resolver.resolve(&mut contact(0.0, 0.0), a, wall);
+        assert_eq!((r.v.x, r.v.y), (-1.0, 1.0));
+    }
+
+    #[test]
+    fn slow_contacts_do_not_bounce() {
+        let mut resolver = ClassicImpulseResolver::default();
+        let wall = body(Vector::zero(), 0.0, 0.0);
+        let mut a = body(Vector::new(2.0, 0.0), 1.0, 0.0);
+        a.cr = 0.5;
+
+        // Averaged with the wall the restitution is 0.75.
+        let (r, _) = resolver.resolve(&mut contact(0.0, 0.0), a, wall);
+        assert_eq!(r.v.x, -1.5);
+
+        resolver.restitution_combine = CombineRule::Min;
+        let (r, _) = resolver.resolve(&mut contact(0.0, 0.0), a, wall);
+        assert_eq!(r.v.x, -1.0);
+
+        // Below the threshold the body just stops against the wall.
+        a.v = Vector::new(0.5, 0.0);
+        let (r, _) = resolver.resolve(&mut contact(0.0, 0.0), a, wall);
+        assert_eq!(r.v.x, 0.0);
+
+        resolver.restitution_threshold = 0.0;
+        let (r, _) = resolver.resolve(&mut contact(0.0, 0.0), a, wall);
+        assert_eq!(r.v.x, -0.25);
+    }
+}
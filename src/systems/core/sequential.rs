@@ -0,0 +1,207 @@
+use bevy_inspector_egui::Inspectable;
+use prima::prelude::*;
+
+use crate::{components::CombineRule, pipeline::{ImpulseObject, ImpulseResult, Manifold}};
+
+use super::{cross, cross_scalar, ImpulseResolver};
+
+/// An iterative resolver. Every manifold is visited several times per step, and each visit only nudges the impulse
+/// accumulated on that contact so far, clamped so it never pulls bodies together. Contacts that survive from one step to
+/// the next start from the impulse they finished on. Slower than [super::ClassicImpulseResolver], but stacks of boxes
+/// settle rather than sinking into each other.
+#[derive(Debug, Clone, Copy, Inspectable)]
+pub struct SequentialImpulseResolver {
+    /// Number of passes over the manifolds each step.
+    #[inspectable(min = 1, max = 50)]
+    pub iterations: u32,
+    #[inspectable(min = 0.0, max = 1.0)]
+    pub correction: f32,
+    #[inspectable(min = 0.0, max = 0.1)]
+    pub slop: f32,
+    /// Used to merge restitution coefficients, unless one of the materials asks otherwise.
+    pub restitution_combine: CombineRule,
+    /// Contacts closing slower than this do not bounce at all, which stops resting stacks from jittering.
+    #[inspectable(min = 0.0)]
+    pub restitution_threshold: f32,
+    /// Used to merge friction coefficients, unless one of the materials asks otherwise.
+    pub friction_combine: CombineRule,
+    /// How much of last step's accumulated impulse is applied up front. 0.0 starts every step cold.
+    #[inspectable(min = 0.0, max = 1.0)]
+    pub warm_start: f32,
+}
+
+impl Default for SequentialImpulseResolver {
+    fn default() -> Self {
+        Self {
+            iterations: 8,
+            correction: 0.8,
+            slop: 0.01,
+            restitution_combine: CombineRule::Average,
+            restitution_threshold: 1.0,
+            friction_combine: CombineRule::Average,
+            warm_start: 0.8,
+        }
+    }
+}
+
+impl ImpulseResolver for SequentialImpulseResolver {
+    fn tick(&mut self) {}
+
+    fn iterations(&self) -> u32 {
+        self.iterations
+    }
+
+    fn prepare(&mut self, m: &mut Manifold, a: ImpulseObject, b: ImpulseObject) -> (ImpulseResult, ImpulseResult) {
+        let mut r1: ImpulseResult = a.into();
+        let mut r2: ImpulseResult = b.into();
+        let rule = CombineRule::pick(self.restitution_combine, a.rc, b.rc);
+        let t = Vector::new(-m.n.y, m.n.x);
+
+        for point in m.points.iter_mut() {
+            let a_arm = Vector::new(point.c.x - a.c.x, point.c.y - a.c.y);
+            let b_arm = Vector::new(point.c.x - b.c.x, point.c.y - b.c.y);
+            let rv_n = ((a.v + cross_scalar(a.r, a_arm)) - (b.v + cross_scalar(b.r, b_arm))).dot(&m.n);
+
+            // The bounce is worked out from the closing speed before any impulses are applied.
+            point.bias = if rv_n > self.restitution_threshold {
+                rule.combine(a.cr, b.cr) * rv_n
+            } else {
+                0.0
+            };
+
+            // Warm start with whatever the point ended up needing last step, so stacks do not have to rebuild it from nothing.
+            point.jn *= self.warm_start;
+            point.jt *= self.warm_start;
+            let impulse = m.n * -point.jn + t * -point.jt;
+            apply(&mut r1, &mut r2, &a, &b, a_arm, b_arm, impulse);
+        }
+
+        (r1, r2)
+    }
+
+    fn resolve(&mut self, m: &mut Manifold, a: ImpulseObject, b: ImpulseObject) -> (ImpulseResult, ImpulseResult) {
+        let mut r1: ImpulseResult = a.into();
+        let mut r2: ImpulseResult = b.into();
+
+        let n = m.n;
+        let t = Vector::new(-n.y, n.x);
+        let rule = CombineRule::pick(self.friction_combine, a.fc, b.fc);
+        let static_friction = rule.combine(a.sf, b.sf);
+        let dynamic_friction = rule.combine(a.df, b.df);
+
+        // Each point sees the velocities left behind by the ones before it.
+        for point in m.points.iter_mut() {
+            let a_arm = Vector::new(point.c.x - a.c.x, point.c.y - a.c.y);
+            let b_arm = Vector::new(point.c.x - b.c.x, point.c.y - b.c.y);
+
+            // Normal impulse. Positive values push the bodies apart.
+            let a_rn = cross(a_arm, n);
+            let b_rn = cross(b_arm, n);
+            let k_n = a.i + b.i + a_rn * a_rn * a.mi + b_rn * b_rn * b.mi;
+            if k_n <= 0.0 {
+                continue;
+            }
+
+            let rv = (r1.v + cross_scalar(r1.r, a_arm)) - (r2.v + cross_scalar(r2.r, b_arm));
+            let lambda = (rv.dot(&n) + point.bias) / k_n;
+            let total = (point.jn + lambda).max(0.0);
+            let lambda = total - point.jn;
+            point.jn = total;
+            apply(&mut r1, &mut r2, &a, &b, a_arm, b_arm, n * -lambda);
+
+            // Friction impulse, clamped to Coulomb's cone around the accumulated normal impulse.
+            let a_rt = cross(a_arm, t);
+            let b_rt = cross(b_arm, t);
+            let k_t = a.i + b.i + a_rt * a_rt * a.mi + b_rt * b_rt * b.mi;
+            if k_t > 0.0 {
+                let rv = (r1.v + cross_scalar(r1.r, a_arm)) - (r2.v + cross_scalar(r2.r, b_arm));
+                let lambda = rv.dot(&t) / k_t;
+                let mut total = point.jt + lambda;
+                if total.abs() > point.jn * static_friction {
+                    total = total.signum() * point.jn * dynamic_friction;
+                }
+                let lambda = total - point.jt;
+                point.jt = total;
+                apply(&mut r1, &mut r2, &a, &b, a_arm, b_arm, t * -lambda);
+            }
+        }
+
+        // Positional correction, from the deepest point.
+        if a.i + b.i > 0.0 {
+            let correction = n * (m.depth() - self.slop).max(0.0) / (a.i + b.i) * self.correction;
+            r1.t = -correction * a.i;
+            r2.t = correction * b.i;
+        }
+
+        (r1, r2)
+    }
+}
+
+/// Applies an impulse to 'a' at the contact, and the opposite impulse to 'b'.
+fn apply(
+    r1: &mut ImpulseResult,
+    r2: &mut ImpulseResult,
+    a: &ImpulseObject,
+    b: &ImpulseObject,
+    a_arm: Vector<f32>,
+    b_arm: Vector<f32>,
+    impulse: Vector<f32>,
+) {
+    r1.v = r1.v + (impulse * a.i);
+    r2.v = r2.v - (impulse * b.i);
+    r1.r += cross(a_arm, impulse) * a.mi;
+    r2.r -= cross(b_arm, impulse) * b.mi;
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::Entity;
+
+    use super::*;
+    use crate::pipeline::ContactPoint;
+
+    fn body(i: f32) -> ImpulseObject {
+        ImpulseObject {
+            m: if i > 0.0 { 1.0 / i } else { 0.0 },
+            i,
+            v: Vector::zero(),
+            r: 0.0,
+            c: Point::new(0.0, 0.0),
+            mi: 0.0,
+            cr: 0.0,
+            rc: None,
+            sf: 0.0,
+            df: 0.0,
+            fc: None,
+        }
+    }
+
+    #[test]
+    fn warm_start_applies_last_steps_impulse() {
+        let mut resolver = SequentialImpulseResolver {
+            warm_start: 0.5,
+            ..Default::default()
+        };
+        let mut m = Manifold {
+            a: Entity::from_raw(0),
+            b: Entity::from_raw(1),
+            n: Vector::new(1.0, 0.0),
+            f: None,
+            points: vec![ContactPoint {
+                c: Point::new(0.0, 0.0),
+                p: 0.0,
+                id: 0,
+                jn: 2.0,
+                jt: 0.0,
+                bias: 0.0,
+            }],
+            part_a: None,
+            part_b: None,
+        };
+
+        // Resting against the wall, half of last step's impulse pushes the body away before any iterations.
+        let (a, _) = resolver.prepare(&mut m, body(1.0), body(0.0));
+        assert_eq!((a.v.x, a.v.y), (-1.0, 0.0));
+        assert_eq!(m.points[0].jn, 1.0);
+    }
+}
@@ -0,0 +1,268 @@
+use bevy::{prelude::*, utils::{HashMap, HashSet}};
+use prima::prelude::*;
+
+use crate::{
+    components::{BodyType, Forces, PartId, RigidBody, Sleep, Velocity},
+    pipeline::{Manifolds, PendingRemovals},
+    resources::FishicsConfig,
+};
+
+/// Puts still bodies to sleep and wakes them again. Dynamic bodies joined by contacts form islands, and an island only
+/// sleeps once every body in it has been still for long enough. Anything in the island moving, being pushed by a
+/// moving kinematic body or having a force applied wakes the whole island up.
+pub fn sleeping(
+    cfg: Res<FishicsConfig>,
+    manifolds: Res<Manifolds>,
+    mut bodies: Query<(Entity, Option<&mut Velocity>, Option<&mut Sleep>, Option<&BodyType>), With<RigidBody>>,
+    pushed: Query<Entity, (With<Sleep>, Changed<Forces>)>,
+) {
+    let dt = cfg.dt();
+    let linear = cfg.sleep_linear_threshold * cfg.sleep_linear_threshold;
+    let is_still = |velocity: Option<&Velocity>| {
+        velocity.map_or(true, |v| {
+            v.magnitide_squared() <= linear && v.angular().abs() <= cfg.sleep_angular_threshold
+        })
+    };
+
+    // Every dynamic body is a node, and only bodies that can sleep and have been still long enough are ready to.
+    let mut lookup = HashMap::default();
+    let mut entities = Vec::new();
+    let mut ready = Vec::new();
+    let mut moving = HashSet::default();
+    for (entity, velocity, sleep, body_type) in bodies.iter_mut() {
+        let still = is_still(velocity.as_deref());
+        if !body_type.copied().unwrap_or_default().is_dynamic() {
+            if !still {
+                moving.insert(entity);
+            }
+            continue;
+        }
+        let can_sleep = match sleep {
+            // Integration leaves the forces of sleeping bodies alone, so any change has come from outside.
+            Some(mut sleep) if sleep.is_asleep() && pushed.get(entity).is_ok() => {
+                sleep.wake_up();
+                false
+            }
+            Some(sleep) if sleep.is_asleep() => true,
+            Some(mut sleep) => {
+                sleep.tick(still, dt);
+                sleep.idle_time() >= cfg.time_to_sleep
+            }
+            None => false,
+        };
+        lookup.insert(entity, entities.len());
+        entities.push(entity);
+        ready.push(can_sleep);
+    }
+
+    let mut islands = Islands::new(entities.len());
+    for manifold in manifolds.iter() {
+        match (lookup.get(&manifold.a), lookup.get(&manifold.b)) {
+            (Some(a), Some(b)) => islands.join(*a, *b),
+            // Being shoved by a moving kinematic body is as good as moving.
+            (Some(a), None) if moving.contains(&manifold.b) => ready[*a] = false,
+            (None, Some(b)) if moving.contains(&manifold.a) => ready[*b] = false,
+            _ => {}
+        }
+    }
+    // Sleeping bodies keep their contacts, but an island stays whole even if one of the contacts holding it together goes.
+    for (index, entity) in entities.iter().enumerate() {
+        let island = bodies
+            .get(*entity)
+            .ok()
+            .and_then(|(_, _, sleep, _)| sleep.and_then(|sleep| sleep.island()))
+            .and_then(|island| lookup.get(&island));
+        if let Some(island) = island {
+            islands.join(index, *island);
+        }
+    }
+
+    let mut island_ready = vec![true; entities.len()];
+    for (index, ready) in ready.iter().enumerate() {
+        let root = islands.root(index);
+        island_ready[root] &= *ready;
+    }
+
+    for (index, entity) in entities.iter().enumerate() {
+        let root = islands.root(index);
+        if let Ok((_, velocity, Some(mut sleep), _)) = bodies.get_mut(*entity) {
+            if !island_ready[root] {
+                if sleep.is_asleep() || sleep.island().is_some() {
+                    sleep.stir();
+                }
+            } else if !sleep.is_asleep() {
+                sleep.fall_asleep(entities[root]);
+                if let Some(mut velocity) = velocity {
+                    velocity.set_linear(Vector::new(0.0, 0.0));
+                    velocity.set_angular(0.0);
+                }
+            } else if sleep.island() != Some(entities[root]) {
+                // Islands can merge while asleep, so everyone has to agree on who they are sleeping with.
+                sleep.fall_asleep(entities[root]);
+            }
+        }
+    }
+}
+
+/// Wakes every body that was touching something which has since been removed, as it may have been resting on it. The
+/// rest of its island wakes along with it once [sleeping] sees it moving again.
+pub fn wake_removed_contacts(
+    removals: Res<PendingRemovals>,
+    manifolds: Res<Manifolds>,
+    mut sleeping: Query<&mut Sleep>,
+) {
+    if removals.0.is_empty() {
+        return;
+    }
+    // A child collider is removed from its own entity, but the contact is stored against its parent body.
+    let removed = |entity: Entity, part: Option<PartId>| {
+        removals.0.contains(&entity) || matches!(part, Some(PartId::Child(child)) if removals.0.contains(&child))
+    };
+    for manifold in manifolds.iter() {
+        if !removed(manifold.a, manifold.part_a) && !removed(manifold.b, manifold.part_b) {
+            continue;
+        }
+        for entity in [manifold.a, manifold.b] {
+            if let Ok(mut sleep) = sleeping.get_mut(entity) {
+                if sleep.is_asleep() {
+                    sleep.wake_up();
+                }
+            }
+        }
+    }
+}
+
+/// True if the body cannot move by itself, either because it is static or because it is asleep. Pairs of idle bodies
+/// are left out of the broad phase, and keep whatever contacts they had when they went idle.
+pub(crate) fn is_idle(entity: Entity, types: &Query<&BodyType>, sleeping: &Query<&Sleep>) -> bool {
+    types.get(entity).map_or(false, |t| t.is_static()) || sleeping.get(entity).map_or(false, |s| s.is_asleep())
+}
+
+/// Union-find over the indices of the dynamic bodies.
+struct Islands {
+    parents: Vec<usize>,
+}
+
+impl Islands {
+    fn new(len: usize) -> Self {
+        Self {
+            parents: (0..len).collect(),
+        }
+    }
+
+    fn root(&mut self, mut index: usize) -> usize {
+        while self.parents[index] != index {
+            self.parents[index] = self.parents[self.parents[index]];
+            index = self.parents[index];
+        }
+        index
+    }
+
+    fn join(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.root(a), self.root(b));
+        if a != b {
+            self.parents[b] = a;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::{ContactPoint, Manifold};
+
+    fn touching(a: Entity, b: Entity) -> Manifold {
+        Manifold {
+            a,
+            b,
+            n: Vector::new(1.0, 0.0),
+            f: None,
+            points: vec![ContactPoint {
+                c: Point::new(0.0, 0.0),
+                p: 0.0,
+                id: 0,
+                jn: 0.0,
+                jt: 0.0,
+                bias: 0.0,
+            }],
+            part_a: None,
+            part_b: None,
+        }
+    }
+
+    fn body(world: &mut World, velocity: Vec2) -> Entity {
+        world
+            .spawn()
+            .insert_bundle((RigidBody::default(), Velocity::new(velocity, 0.0), Sleep::default()))
+            .id()
+    }
+
+    fn asleep(world: &World, entity: Entity) -> bool {
+        world.get::<Sleep>(entity).unwrap().is_asleep()
+    }
+
+    /// Runs enough steps for anything still to fall asleep.
+    fn settle(world: &mut World, stage: &mut SystemStage) {
+        let cfg = world.resource::<FishicsConfig>();
+        let steps = (cfg.time_to_sleep / cfg.dt()).ceil() as usize + 1;
+        for _ in 0..steps {
+            stage.run(world);
+        }
+    }
+
+    fn world() -> World {
+        let mut world = World::new();
+        world.insert_resource(FishicsConfig::default());
+        world.insert_resource(Manifolds::new());
+        world.insert_resource(PendingRemovals::default());
+        world
+    }
+
+    #[test]
+    fn islands_sleep_and_wake_together() {
+        let mut world = world();
+        let (a, b) = (body(&mut world, Vec2::ZERO), body(&mut world, Vec2::ZERO));
+        let c = body(&mut world, Vec2::new(1.0, 0.0));
+        world.resource_mut::<Manifolds>().set(vec![touching(a, b)]);
+
+        let mut stage = SystemStage::single(sleeping);
+        settle(&mut world, &mut stage);
+        assert!(asleep(&world, a) && asleep(&world, b));
+        assert_eq!(world.get::<Sleep>(a).unwrap().island(), world.get::<Sleep>(b).unwrap().island());
+        assert!(!asleep(&world, c));
+
+        // Waking one wakes the whole island on the next step, even though their contact has gone.
+        world.resource_mut::<Manifolds>().set(Vec::new());
+        world.get_mut::<Sleep>(a).unwrap().wake_up();
+        stage.run(&mut world);
+        assert!(!asleep(&world, a) && !asleep(&world, b));
+    }
+
+    #[test]
+    fn moving_kinematic_bodies_and_removals_wake_sleepers() {
+        let mut world = world();
+        let (a, b) = (body(&mut world, Vec2::ZERO), body(&mut world, Vec2::ZERO));
+        let kinematic = body(&mut world, Vec2::ZERO);
+        world.entity_mut(kinematic).insert(BodyType::KinematicVelocity);
+        world.resource_mut::<Manifolds>().set(vec![touching(a, b), touching(kinematic, b)]);
+
+        let mut stage = SystemStage::single(sleeping);
+        settle(&mut world, &mut stage);
+        assert!(asleep(&world, a) && asleep(&world, b));
+
+        world.get_mut::<Velocity>(kinematic).unwrap().set_linear(Vector::new(1.0, 0.0));
+        stage.run(&mut world);
+        assert!(!asleep(&world, a) && !asleep(&world, b));
+
+        world.get_mut::<Velocity>(kinematic).unwrap().set_linear(Vector::new(0.0, 0.0));
+        settle(&mut world, &mut stage);
+        assert!(asleep(&world, a) && asleep(&world, b));
+
+        // Whatever 'b' was resting on is gone, so it wakes along with everything it was sleeping with.
+        world.despawn(a);
+        world.resource_mut::<PendingRemovals>().0.push(a);
+        SystemStage::single(wake_removed_contacts).run(&mut world);
+        stage.run(&mut world);
+        assert!(!asleep(&world, b));
+    }
+}
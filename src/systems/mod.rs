@@ -1,3 +1,4 @@
 pub mod core;
 pub mod render;
-pub mod transforms;
\ No newline at end of file
+pub mod time;
+pub mod transforms;
@@ -1,29 +1,71 @@
-use crate::{AbstractShape, components::{ColliderRender, Collider}, resources::FishicsConfig};
+use crate::{
+    AbstractShape,
+    components::{ColliderRender, Collider, ColliderOffset, CompoundCollider, RigidBody},
+    resources::FishicsConfig,
+};
 use bevy::{prelude::*, sprite::MaterialMesh2dBundle};
 
+/// How thick lines without any thickness of their own are drawn.
+const LINE_RENDER_RADIUS: f32 = 0.02;
+
+/// Marks a body whose colliders have been given meshes.
+#[derive(Component)]
+pub struct ColliderRendered;
+
 pub fn create_mesh_renders(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
     cfg: Res<FishicsConfig>,
-    colliders: Query<&Collider>,
-    new_renderables: Query<(Entity, &ColliderRender), Without<Handle<Mesh>>>,
+    new_bodies: Query<
+        (Entity, &ColliderRender, Option<&Collider>, Option<&CompoundCollider>, Option<&Transform>),
+        (With<RigidBody>, Without<ColliderRendered>),
+    >,
+    new_children: Query<(Entity, &ColliderRender, &Collider, Option<&ColliderOffset>), (Without<RigidBody>, Without<Handle<Mesh>>)>,
 ) {
-    for (entity, col_renderer) in new_renderables.iter() {
-        if let Some(collider) = colliders.get(entity).ok() {
-            if let Some((mesh, scale)) = generate_mesh(collider.shape) {
-                let transform = Transform::from_scale(scale * cfg.scale);
-
+    // Every shape on a body gets a child of its own, drawn in the body's colour, so the body's transform is never
+    // scaled and anything else attached to it keeps its size.
+    for (entity, col_renderer, collider, compound, transform) in new_bodies.iter() {
+        let material = materials.add(col_renderer.colour.into());
+        let shapes = collider
+            .map(|collider| (&collider.shape, Vec2::ZERO, 0.0))
+            .into_iter()
+            .chain(compound.into_iter().flat_map(|compound| {
+                compound.parts.iter().map(|part| (&part.shape, part.offset, part.rotation))
+            }));
+        let mut children = Vec::new();
+        for (shape, offset, rotation) in shapes {
+            if let Some((mesh, scale)) = generate_mesh(shape) {
                 let bundle = MaterialMesh2dBundle {
                     mesh: meshes.add(mesh).into(),
-                    material: materials.add(col_renderer.colour.into()),
-                    transform,
+                    material: material.clone(),
+                    transform: part_transform(offset, rotation, scale, &cfg),
                     ..Default::default()
                 };
-
-                commands.entity(entity).insert_bundle(bundle);
+                children.push(commands.spawn_bundle(bundle).id());
             }
         }
+
+        let mut body = commands.entity(entity);
+        if transform.is_none() {
+            body.insert_bundle((Transform::default(), GlobalTransform::default()));
+        }
+        body.insert(ColliderRendered).push_children(&children);
+    }
+
+    // Child colliders are already children of their body, so they carry their own mesh.
+    for (entity, col_renderer, collider, offset) in new_children.iter() {
+        if let Some((mesh, scale)) = generate_mesh(&collider.shape) {
+            let offset = offset.copied().unwrap_or_default();
+            let bundle = MaterialMesh2dBundle {
+                mesh: meshes.add(mesh).into(),
+                material: materials.add(col_renderer.colour.into()),
+                transform: part_transform(offset.translation, offset.rotation, scale, &cfg),
+                ..Default::default()
+            };
+
+            commands.entity(entity).insert_bundle(bundle);
+        }
     }
 }
 
@@ -32,8 +74,17 @@ pub fn update_mesh_renders() {}
 // ============================================================================
 // ============================================================================
 
-fn generate_mesh(shape: AbstractShape) -> Option<(Mesh, Vec3)> {
-    match shape {
+/// Places a shape's mesh relative to its body.
+fn part_transform(offset: Vec2, rotation: f32, scale: Vec3, cfg: &FishicsConfig) -> Transform {
+    Transform {
+        translation: offset.extend(0.0) * cfg.scale,
+        rotation: Quat::from_rotation_z(rotation),
+        scale: scale * cfg.scale,
+    }
+}
+
+fn generate_mesh(shape: &AbstractShape) -> Option<(Mesh, Vec3)> {
+    match *shape {
         AbstractShape::Circle { radius } => {
             let mesh = crate::build_circle(radius, 32);
             Some((mesh, Vec3::new(1.0, 1.0, 1.0)))
@@ -46,6 +97,19 @@ fn generate_mesh(shape: AbstractShape) -> Option<(Mesh, Vec3)> {
             let scale = Vec3::new(width, height, 1.0);
             Some((mesh, scale))
         }
-        AbstractShape::Line { start: _, end: _ } => None,
+        AbstractShape::Line { start, end, thickness } => {
+            // Bare lines still get drawn, just thin.
+            let radius = (thickness / 2.0).max(LINE_RENDER_RADIUS);
+            let mesh = crate::build_line(start, end, radius, 8);
+            Some((mesh, Vec3::new(1.0, 1.0, 1.0)))
+        }
+        AbstractShape::Capsule { half_height, radius } => {
+            let mesh = crate::build_line(Vec2::new(0.0, -half_height), Vec2::new(0.0, half_height), radius, 16);
+            Some((mesh, Vec3::new(1.0, 1.0, 1.0)))
+        }
+        AbstractShape::Polygon { ref vertices } => {
+            let mesh = crate::build_polygon(&crate::convex_vertices(vertices));
+            Some((mesh, Vec3::new(1.0, 1.0, 1.0)))
+        }
     }
 }
@@ -0,0 +1,84 @@
+use bevy::{ecs::schedule::ShouldRun, prelude::*};
+use prima::prelude::*;
+
+use crate::{
+    components::{BodyType, Collider, CompoundCollider, PreviousRigidBody, RigidBody, Velocity},
+    pipeline::PendingRemovals,
+    resources::{FishicsConfig, PhysicsTime},
+};
+
+/// Run criteria for the physics stage. Keeps the stage looping until the accumulated frame time has been stepped through,
+/// running once for every substep.
+pub fn fixed_timestep(
+    time: Res<Time>,
+    cfg: Res<FishicsConfig>,
+    mut physics_time: ResMut<PhysicsTime>,
+) -> ShouldRun {
+    if physics_time.tick(time.delta_seconds() * cfg.time, cfg.timestep, cfg.max_steps, cfg.substeps.max(1)) {
+        ShouldRun::YesAndCheckAgain
+    } else {
+        ShouldRun::No
+    }
+}
+
+/// Run criteria for systems in the physics stage that only run once a step, before any of its substeps.
+pub fn first_substep(physics_time: Res<PhysicsTime>) -> ShouldRun {
+    if physics_time.substep() == 0 {
+        ShouldRun::Yes
+    } else {
+        ShouldRun::No
+    }
+}
+
+/// Run criteria for systems in the physics stage that only run once a step, after all of its substeps.
+pub fn last_substep(cfg: Res<FishicsConfig>, physics_time: Res<PhysicsTime>) -> ShouldRun {
+    if physics_time.substep() + 1 >= cfg.substeps.max(1) {
+        ShouldRun::Yes
+    } else {
+        ShouldRun::No
+    }
+}
+
+/// Remembers where every body was at the start of the step, so rendering can be interpolated. Position driven kinematic
+/// bodies get their velocity from how far they have been moved since. They are moved outside the pipeline, so that is
+/// worked out on the first step of each frame and spread over every step the frame takes.
+pub fn store_previous_state(
+    mut commands: Commands,
+    cfg: Res<FishicsConfig>,
+    time: Res<PhysicsTime>,
+    mut tracked: Query<(&RigidBody, &mut PreviousRigidBody, Option<&BodyType>, Option<&mut Velocity>)>,
+    untracked: Query<(Entity, &RigidBody), Without<PreviousRigidBody>>,
+) {
+    let span = cfg.timestep * time.frame_steps().max(1) as f32;
+    let first_step = time.steps() <= 1;
+    for (rb, mut previous, body_type, velocity) in tracked.iter_mut() {
+        if let (Some(BodyType::KinematicPosition), Some(mut velocity)) = (body_type, velocity) {
+            if first_step && span > 0.0 {
+                let moved = rb.position - previous.0.position;
+                // Rotation is stored in half turns, so take the short way round before converting to radians.
+                let turned = (rb.rotation - previous.0.rotation + 1.0).rem_euclid(2.0) - 1.0;
+                velocity.set_linear(Vector::new(moved.x / span, moved.y / span));
+                velocity.set_angular(turned * std::f32::consts::PI / span);
+            }
+        }
+        previous.0 = *rb;
+    }
+    for (entity, rb) in untracked.iter() {
+        commands.entity(entity).insert(PreviousRigidBody(*rb));
+    }
+}
+
+/// The physics stage does not run every frame, so removals are collected at the end of each frame before bevy forgets them.
+pub fn collect_removals(
+    mut pending: ResMut<PendingRemovals>,
+    removed_bodies: RemovedComponents<RigidBody>,
+    removed_colliders: RemovedComponents<Collider>,
+    removed_compounds: RemovedComponents<CompoundCollider>,
+) {
+    pending.0.extend(
+        removed_bodies
+            .iter()
+            .chain(removed_colliders.iter())
+            .chain(removed_compounds.iter()),
+    );
+}
@@ -2,7 +2,7 @@
 use bevy::prelude::*;
 use prima::prelude::*;
 
-use crate::{resources::FishicsConfig, components::{Velocity, RigidBody, Mass}};
+use crate::{resources::{FishicsConfig, PhysicsTime}, components::{Velocity, RigidBody, PreviousRigidBody, Mass}};
 
 pub fn speed_limmit(cfg: Res<FishicsConfig>, mut vel: Query<&mut Velocity>) {
     for mut vel in vel.iter_mut() {
@@ -14,12 +14,17 @@ pub fn speed_limmit(cfg: Res<FishicsConfig>, mut vel: Query<&mut Velocity>) {
     }
 }
 
-/// Applies the [RigidBody] values to bevy's [Transform].
+/// Applies the [RigidBody] values to bevy's [Transform], interpolating between the last two physics steps.
 pub fn apply_transforms(
     cfg: Res<FishicsConfig>,
-    mut bodies: Query<(&mut Transform, &RigidBody, Option<&Mass>)>,
+    physics_time: Res<PhysicsTime>,
+    mut bodies: Query<(&mut Transform, &RigidBody, Option<&PreviousRigidBody>, Option<&Mass>)>,
 ) {
-    for (mut transform, rigid_body, mass) in bodies.iter_mut() {
+    for (mut transform, rigid_body, previous, mass) in bodies.iter_mut() {
+        let rigid_body = match previous {
+            Some(previous) => previous.0.lerp(rigid_body, physics_time.alpha()),
+            None => *rigid_body,
+        };
         let z = if let Some(mass) = mass {
             mass.inv()
         } else {